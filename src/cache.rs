@@ -0,0 +1,95 @@
+use std::io;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::store::DataFile;
+
+const DATA_DIR: &str = "scan_cache";
+
+/// Persists the result of the last full scan so the list can be shown
+/// before the new scan completes. Each cache key gets its own file, so
+/// keybindings scanning different root dirs don't replace each other's
+/// cache. The first line records the key, in case two keys share a file.
+#[derive(Debug)]
+pub struct ScanCache {
+    file: DataFile,
+    key: String,
+}
+
+impl ScanCache {
+    pub fn new(dir: &Path, key: String) -> Self {
+        Self {
+            file: DataFile::new(&dir.join(DATA_DIR), &file_name(&key)),
+            key,
+        }
+    }
+
+    pub fn load(&self) -> Vec<String> {
+        self.file
+            .read()
+            .map(|content| parse(&content, &self.key))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dirs: &[String]) -> io::Result<()> {
+        self.file.write(&serialize(&self.key, dirs))
+    }
+}
+
+/// Identifies the root dirs, root files and filter a scan was made for, as
+/// written in the config so the cache can be read before `~` and variables
+/// are expanded. Relative root dirs are identified by the cwd.
+pub fn cache_key(config: &Config, cwd: &Path) -> String {
+    let root_dirs: Vec<String> = config
+        .root_dirs
+        .iter()
+        .map(|r| format!("{}:{}", r.path.display(), config.depth_for(r)))
+        .collect();
+    format!(
+        "{}|{}|{}|{}",
+        cwd.display(),
+        root_dirs.join(";"),
+        config.root_files,
+        config.root_dirs_filter.join(";")
+    )
+}
+
+// FNV-1a, unlike the std hasher it doesn't change between builds
+fn file_name(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+fn parse(content: &str, key: &str) -> Vec<String> {
+    let mut lines = content.lines();
+    if lines.next() != Some(key) {
+        return vec![];
+    }
+    lines.map(|l| l.to_string()).collect()
+}
+
+fn serialize(key: &str, dirs: &[String]) -> String {
+    let mut content = format!("{key}\n");
+    dirs.iter().for_each(|dir| {
+        content.push_str(dir);
+        content.push('\n');
+    });
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_is_keyed_by_root_dirs() {
+        let dirs = vec!["/home/me/a".to_string(), "/home/me/b".to_string()];
+        let content = serialize("/home/me", &dirs);
+        assert_eq!(parse(&content, "/home/me"), dirs);
+        assert!(parse(&content, "/home/other").is_empty());
+        assert_ne!(file_name("/home/me"), file_name("/home/other"));
+        assert_eq!(file_name("/home/me"), file_name("/home/me"));
+    }
+}
@@ -1,25 +1,207 @@
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 use zellij_tile::prelude::LayoutInfo;
 
-use crate::ROOT;
+use crate::error::Error;
+use crate::glob::glob_match;
+use crate::ignore::IgnoreRules;
+use crate::keymap::Keymap;
+use crate::markers::{MarkerMode, RootMarkers};
+use crate::naming::SessionNaming;
+use crate::paths;
+use crate::Screen;
+
+const DEFAULT_MAX_DEPTH: usize = 1;
+const DEFAULT_INCLUDE_HIDDEN: &str = ".config";
+
+// The default root dir is the plugin cwd, relative paths are relative to it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RootDir {
+    pub path: PathBuf,
+    pub max_depth: Option<usize>, // Overrides Config::max_depth for this root
+}
 
 #[derive(Debug)]
 pub struct Config {
-    pub root_dirs: Vec<PathBuf>,  // Directories to search in
+    pub root_dirs: Vec<RootDir>,  // Directories to search in
     pub dirs: Vec<PathBuf>,       // Specific directories to include directly
     pub layout: LayoutInfo,
-    pub root_files: Vec<String>,
+    pub root_layouts: Vec<(PathBuf, LayoutInfo)>, // Layout for the projects of a root dir
+    pub layout_rules: Vec<(String, LayoutInfo)>,  // Layout for the projects matching a glob
+    pub root_files: RootMarkers,  // Which directories are projects
+    pub exclude: IgnoreRules,     // Directories skipped while scanning
+    pub include_hidden: IgnoreRules, // Hidden directories that are still scanned
+    pub max_depth: usize,         // How many levels below a root dir to look for projects
+    pub git_worktrees: bool,      // List the worktrees of discovered git repos
+    pub zoxide: bool,             // Also list the dirs known to zoxide
+    pub dirs_command: Option<String>, // Also list the dirs printed by this command
+    pub session_naming: SessionNaming,
+    pub keymap: Keymap,
+    pub confirm_actions: bool,    // Ask before killing, deleting or disconnecting
+    pub initial_query: String,
+    pub initial_screen: Screen,
+    pub root_dirs_filter: Vec<String>, // Only the root dirs matching one of these are scanned
+    pub open_dir: Option<PathBuf>,    // Opened without showing the picker
+    pub open_session: Option<String>, // Attached without showing the picker
+    pub data_dir: Option<PathBuf>,    // Where the frecency and the scan cache are kept
+    pub warnings: Vec<Error>,         // Settings that couldn't be parsed, reported once loaded
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            root_dirs: vec![PathBuf::from(ROOT)],
+            root_dirs: vec![RootDir::default()],
             dirs: vec![],
             layout: LayoutInfo::BuiltIn("default".to_string()),
-            root_files: vec![".git".to_string()]
+            root_layouts: vec![],
+            layout_rules: vec![],
+            root_files: RootMarkers::default(),
+            exclude: IgnoreRules::default(),
+            include_hidden: IgnoreRules::from(DEFAULT_INCLUDE_HIDDEN),
+            max_depth: DEFAULT_MAX_DEPTH,
+            git_worktrees: false,
+            zoxide: false,
+            dirs_command: None,
+            session_naming: SessionNaming::default(),
+            keymap: Keymap::default(),
+            confirm_actions: true,
+            initial_query: String::new(),
+            initial_screen: Screen::default(),
+            root_dirs_filter: vec![],
+            open_dir: None,
+            open_session: None,
+            data_dir: None,
+            warnings: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn depth_for(&self, root_dir: &RootDir) -> usize {
+        root_dir.max_depth.unwrap_or(self.max_depth)
+    }
+
+    /// The root dirs that can contain listed dirs: the ones inside a filter
+    /// path and the ones containing it. A glob may match anywhere below a
+    /// root, so it keeps them all, `is_listed` then filters what is found.
+    pub fn scanned_root_dirs(&self) -> impl Iterator<Item = &RootDir> {
+        self.root_dirs.iter().filter(|r| {
+            self.is_listed(&r.path)
+                || self
+                    .root_dirs_filter
+                    .iter()
+                    .any(|f| f.contains(['*', '?']) || Path::new(f).starts_with(&r.path))
+        })
+    }
+
+    /// Whether the dir is inside a path matching root_dirs_filter, whichever
+    /// source found it
+    pub fn is_listed(&self, dir: &Path) -> bool {
+        self.root_dirs_filter.is_empty()
+            || dir.ancestors().any(|dir| {
+                let path = dir.to_string_lossy();
+                self.root_dirs_filter.iter().any(|f| glob_match(f, path.trim_end_matches('/')))
+            })
+    }
+
+    fn layouts(&self) -> impl Iterator<Item = &LayoutInfo> {
+        std::iter::once(&self.layout)
+            .chain(self.root_layouts.iter().map(|(_, layout)| layout))
+            .chain(self.layout_rules.iter().map(|(_, layout)| layout))
+    }
+
+    fn layouts_mut(&mut self) -> impl Iterator<Item = &mut LayoutInfo> {
+        std::iter::once(&mut self.layout)
+            .chain(self.root_layouts.iter_mut().map(|(_, layout)| layout))
+            .chain(self.layout_rules.iter_mut().map(|(_, layout)| layout))
+    }
+
+    /// Whether some paths use `~` or environment variables
+    pub fn needs_env(&self) -> bool {
+        let layout_file = |layout: &LayoutInfo| match layout {
+            LayoutInfo::File(path) => paths::needs_env(Path::new(path)),
+            _ => false,
+        };
+        self.root_dirs.iter().any(|r| paths::needs_env(&r.path))
+            || self.dirs.iter().any(|d| paths::needs_env(d))
+            || self.open_dir.as_deref().is_some_and(paths::needs_env)
+            || self.data_dir.as_deref().is_some_and(paths::needs_env)
+            || self.root_dirs_filter.iter().any(|f| paths::needs_env(Path::new(f)))
+            || self.root_layouts.iter().any(|(root, _)| paths::needs_env(root))
+            || self.layouts().any(layout_file)
+    }
+
+    /// Makes the configured paths absolute, relative ones are relative to the
+    /// plugin cwd. Layout files are left alone, zellij looks names up in its
+    /// layout dir, and so are the filters, which are globs.
+    pub fn make_absolute(&mut self, cwd: &Path) {
+        let absolute = |path: &mut PathBuf| *path = paths::absolute(path, cwd);
+        self.root_dirs.iter_mut().for_each(|r| absolute(&mut r.path));
+        self.dirs.iter_mut().for_each(absolute);
+        self.root_layouts.iter_mut().for_each(|(root, _)| absolute(root));
+        self.open_dir.iter_mut().for_each(absolute);
+        self.data_dir.iter_mut().for_each(absolute);
+    }
+
+    /// Expands `~` and environment variables in every path, the paths that
+    /// can't be expanded are dropped and returned as warnings
+    pub fn expand_paths(&mut self, env: &HashMap<String, String>) -> Vec<Error> {
+        let mut warnings = vec![];
+        let mut expand = |path: &mut PathBuf| match paths::expand(path, env) {
+            Ok(expanded) => {
+                *path = expanded;
+                true
+            }
+            Err(warning) => {
+                warnings.push(warning);
+                false
+            }
+        };
+        self.root_dirs.retain_mut(|r| expand(&mut r.path));
+        self.dirs.retain_mut(|d| expand(d));
+        // Opening an unexpanded `~/...` would create a session in the wrong dir
+        if self.open_dir.as_mut().is_some_and(|dir| !expand(dir)) {
+            self.open_dir = None;
+        }
+        // The temp dir is used instead
+        if self.data_dir.as_mut().is_some_and(|dir| !expand(dir)) {
+            self.data_dir = None;
+        }
+        for filter in &mut self.root_dirs_filter {
+            let mut path = PathBuf::from(&filter);
+            if expand(&mut path) {
+                *filter = path.to_string_lossy().to_string();
+            }
+        }
+        self.root_layouts.retain_mut(|(root, _)| expand(root));
+        for layout in self.layouts_mut() {
+            if let LayoutInfo::File(file) = layout {
+                let mut path = PathBuf::from(&file);
+                // An unexpandable layout stays as is, zellij reports it when used
+                if expand(&mut path) {
+                    *file = path.to_string_lossy().to_string();
+                }
+            }
+        }
+        warnings
+    }
+}
+
+impl From<&str> for RootDir {
+    // "/path/to/dir" or "/path/to/dir:2"
+    fn from(root_dir: &str) -> Self {
+        if let Some((path, depth)) = root_dir.rsplit_once(':') {
+            if let Ok(depth) = depth.parse::<usize>() {
+                return Self {
+                    path: PathBuf::from(path),
+                    max_depth: Some(depth),
+                };
+            }
+        }
+        Self {
+            path: PathBuf::from(root_dir),
+            max_depth: None,
         }
     }
 }
@@ -34,19 +216,29 @@ fn parse_layout(layout: &str) -> LayoutInfo {
 }
 
 
+// "key=layout;key=layout"
+fn parse_layout_map(layouts: &str) -> Vec<(String, LayoutInfo)> {
+    layouts
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(key, layout)| (key.trim().to_string(), parse_layout(layout.trim())))
+        .collect()
+}
+
 fn parse_dirs(dirs: &str) -> Vec<PathBuf> {
-    return dirs.split(';').map(PathBuf::from).collect()
+    dirs.split(';').map(PathBuf::from).collect()
 }
 
-fn parse_root_files(root_files: &str) -> Vec<String> {
-    return root_files.split(';').map(|s| s.to_string()).collect()
+fn parse_root_dirs(root_dirs: &str) -> Vec<RootDir> {
+    root_dirs.split(';').map(RootDir::from).collect()
 }
 
+
 impl From<BTreeMap<String, String>> for Config {
     fn from(config: BTreeMap<String, String>) -> Self {
-        let root_dirs: Vec<PathBuf> = match config.get("root_dirs") {
-            Some(root_dirs) => parse_dirs(root_dirs),
-            _ => vec![PathBuf::from(ROOT)]
+        let root_dirs: Vec<RootDir> = match config.get("root_dirs") {
+            Some(root_dirs) => parse_root_dirs(root_dirs),
+            _ => vec![RootDir::default()]
         };
         let dirs: Vec<PathBuf> = match config.get("dirs") {
             Some(dirs) => parse_dirs(dirs),
@@ -56,16 +248,178 @@ impl From<BTreeMap<String, String>> for Config {
             Some(layout) => parse_layout(layout),
             _ => LayoutInfo::BuiltIn("default".to_string())
         };
-        let root_files = match config.get("root_files") {
-            Some(root_files) => parse_root_files(root_files),
-            _ => vec![".git".to_string()]
+        let root_layouts = match config.get("root_layouts") {
+            Some(layouts) => parse_layout_map(layouts)
+                .into_iter()
+                .map(|(root, layout)| (PathBuf::from(root), layout))
+                .collect(),
+            _ => vec![]
+        };
+        let layout_rules = match config.get("layout_rules") {
+            Some(rules) => parse_layout_map(rules),
+            _ => vec![]
+        };
+        let mut root_files = match config.get("root_files") {
+            Some(root_files) => RootMarkers::from(root_files.as_str()),
+            _ => RootMarkers::default()
+        };
+        let (keymap, mut warnings) = Keymap::from_config(&config);
+        let mut invalid = |setting: &'static str, value: &str| {
+            warnings.push(Error::InvalidValue {
+                setting,
+                value: value.to_string(),
+            })
+        };
+        match config.get("root_files_match").map(String::as_str) {
+            Some("all") => root_files.mode = MarkerMode::All,
+            Some("any") | None => {}
+            Some(other) => invalid("root_files_match", other),
+        }
+        let exclude = match config.get("exclude") {
+            Some(exclude) => IgnoreRules::from(exclude.as_str()),
+            _ => IgnoreRules::default()
+        };
+        let include_hidden = match config.get("include_hidden") {
+            Some(include_hidden) => IgnoreRules::from(include_hidden.as_str()),
+            _ => IgnoreRules::from(DEFAULT_INCLUDE_HIDDEN)
+        };
+        let max_depth = match config.get("max_depth") {
+            Some(max_depth) => max_depth.parse().unwrap_or_else(|_| {
+                invalid("max_depth", max_depth);
+                DEFAULT_MAX_DEPTH
+            }),
+            _ => DEFAULT_MAX_DEPTH
+        };
+        let initial_screen = match config.get("initial_screen").map(String::as_str) {
+            Some("sessions") => Screen::SearchSessions,
+            Some("unified") => Screen::Unified,
+            Some("dirs") | None => Screen::SearchDirs,
+            Some(other) => {
+                invalid("initial_screen", other);
+                Screen::SearchDirs
+            }
         };
+        let git_worktrees = matches!(config.get("git_worktrees").map(String::as_str), Some("true"));
+        let mut session_naming = SessionNaming::default();
+        if let Some(template) = config.get("session_name_template") {
+            session_naming.template = template.to_string();
+        }
+        if let Some(replacement) = config.get("session_name_replacement") {
+            session_naming.replacement = replacement.to_string();
+        }
+        let confirm_actions = !matches!(config.get("confirm_actions").map(String::as_str), Some("false"));
         Self {
             root_dirs,
             dirs,
             layout,
-            root_files
+            root_layouts,
+            layout_rules,
+            root_files,
+            exclude,
+            include_hidden,
+            max_depth,
+            git_worktrees,
+            zoxide: matches!(config.get("zoxide").map(String::as_str), Some("true")),
+            dirs_command: config.get("dirs_command").cloned(),
+            session_naming,
+            keymap,
+            confirm_actions,
+            initial_query: config.get("initial_query").cloned().unwrap_or_default(),
+            initial_screen,
+            root_dirs_filter: config
+                .get("root_dirs_filter")
+                .map(|filter| filter.split(';').map(|s| s.trim_end_matches('/').to_string()).collect())
+                .unwrap_or_default(),
+            open_dir: config.get("open_dir").map(PathBuf::from),
+            open_session: config.get("open_session").cloned(),
+            data_dir: config.get("data_dir").map(PathBuf::from),
+            warnings,
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_root_dirs_with_depth() {
+        let config = Config::from(BTreeMap::from([
+            ("root_dirs".to_string(), "/home/me/work:2;/home/me/oss".to_string()),
+            ("max_depth".to_string(), "3".to_string()),
+        ]));
+        assert_eq!(config.root_dirs[0].path, PathBuf::from("/home/me/work"));
+        assert_eq!(config.depth_for(&config.root_dirs[0]), 2);
+        assert_eq!(config.root_dirs[1].path, PathBuf::from("/home/me/oss"));
+        assert_eq!(config.depth_for(&config.root_dirs[1]), 3);
+    }
+
+    #[test]
+    fn test_root_dirs_filter() {
+        let config = Config::from(BTreeMap::from([
+            ("root_dirs".to_string(), "/home/me/work;/home/me/oss/".to_string()),
+            ("root_dirs_filter".to_string(), "/home/me/oss/;*/play".to_string()),
+            ("initial_screen".to_string(), "sessions".to_string()),
+        ]));
+        // The glob could match below any root
+        assert_eq!(config.scanned_root_dirs().count(), 2);
+        assert!(config.is_listed(Path::new("/home/me/oss/api")));
+        assert!(config.is_listed(Path::new("/home/me/play/game")));
+        assert!(!config.is_listed(Path::new("/home/me/work/api")));
+        assert_eq!(config.initial_screen, Screen::SearchSessions);
+    }
+
+    #[test]
+    fn test_root_dirs_filter_narrower_than_root() {
+        let config = Config::from(BTreeMap::from([
+            ("root_dirs".to_string(), "/home/me;/srv/www".to_string()),
+            ("root_dirs_filter".to_string(), "/home/me/work".to_string()),
+        ]));
+        let scanned: Vec<&PathBuf> = config.scanned_root_dirs().map(|r| &r.path).collect();
+        assert_eq!(scanned, vec![&PathBuf::from("/home/me")]);
+        assert!(config.is_listed(Path::new("/home/me/work/api")));
+        assert!(!config.is_listed(Path::new("/home/me/oss/api")));
+    }
+
+    #[test]
+    fn test_invalid_values_are_reported() {
+        let config = Config::from(BTreeMap::from([
+            ("initial_screen".to_string(), "session".to_string()),
+            ("max_depth".to_string(), "two".to_string()),
+            ("root_files_match".to_string(), "any".to_string()),
+        ]));
+        assert_eq!(config.initial_screen, Screen::SearchDirs);
+        assert_eq!(config.max_depth, DEFAULT_MAX_DEPTH);
+        assert_eq!(config.warnings.len(), 2);
+        assert!(matches!(
+            &config.warnings[0],
+            Error::InvalidValue { setting: "max_depth", value } if value == "two"
+        ));
+    }
+
+    #[test]
+    fn test_make_absolute() {
+        let mut config = Config::from(BTreeMap::from([
+            ("root_dirs".to_string(), "work:2;/srv/www".to_string()),
+            ("dirs".to_string(), "notes;../shared".to_string()),
+            ("root_layouts".to_string(), "work=:compact".to_string()),
+            ("session_layout".to_string(), "myLayout".to_string()),
+        ]));
+        config.make_absolute(Path::new("/home/me"));
+        assert_eq!(config.root_dirs[0].path, PathBuf::from("/home/me/work"));
+        assert_eq!(config.root_dirs[1].path, PathBuf::from("/srv/www"));
+        assert_eq!(config.dirs, vec![PathBuf::from("/home/me/notes"), PathBuf::from("/home/shared")]);
+        assert_eq!(config.root_layouts[0].0, PathBuf::from("/home/me/work"));
+        assert_eq!(config.layout, LayoutInfo::File("myLayout".to_string()));
+    }
+
+    #[test]
+    fn test_unexpandable_open_dir_is_skipped() {
+        let mut config = Config::from(BTreeMap::from([
+            ("open_dir".to_string(), "$PROJECTS/api".to_string()),
+        ]));
+        let errors = config.expand_paths(&HashMap::new());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(config.open_dir, None);
+    }
+}
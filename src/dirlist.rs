@@ -1,38 +1,116 @@
 use zellij_tile::prelude::*;
 
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
 
-use crate::filter;
+use crate::filter::{self, FilterMatch};
+use crate::marks::Marks;
+use crate::sources::Source;
+use crate::worktree::Worktree;
+
+// Worktrees are rendered indented below their main repo
+const WORKTREE_INDENT: &str = "  ";
 
 #[derive(Debug, Default)]
 pub struct DirList {
-    unique: HashSet<String>,
     dirs: Vec<String>,
+    // Every source listing each dir
+    sources: HashMap<String, BTreeSet<Source>>,
     cursor: usize,
 
     search_term: String,
-    filtered_dirs: Vec<String>,
+    filtered_dirs: Vec<FilterMatch>,
+    // Ranking boost per dir, e.g. from frecency
+    boosts: HashMap<String, u32>,
+    worktrees: HashMap<String, Worktree>,
+    marks: Marks,
 }
 
 impl DirList {
     pub fn reset(&mut self) {
         self.dirs.clear();
+        self.sources.clear();
+        self.worktrees.clear();
         self.cursor = 0;
         self.filtered_dirs.clear();
+        self.marks.clear();
     }
 
-    pub fn update_dirs(&mut self, dirs: Vec<String>) {
-        dirs.iter().for_each(|dir| {
-            if !self.unique.contains(dir) {
-                self.unique.insert(dir.clone());
+    pub fn add_dirs(&mut self, dirs: Vec<String>, source: Source) {
+        for dir in dirs {
+            if !self.sources.contains_key(&dir) {
                 self.dirs.push(dir.clone());
             }
+            self.sources.entry(dir).or_default().insert(source);
+        }
+        self.sort();
+    }
+
+    // Forgets `source`, dropping the dirs no other source lists
+    pub fn remove_source(&mut self, source: Source) {
+        self.sources.values_mut().for_each(|s| {
+            s.remove(&source);
         });
-        self.dirs.sort_by(|a, b| b.cmp(a));
+        self.sources.retain(|_, s| !s.is_empty());
+        self.dirs.retain(|dir| self.sources.contains_key(dir));
+        self.sort();
+    }
+
+    // The listed dirs are kept until the sources find them again
+    pub fn mark_stale(&mut self) {
+        self.sources
+            .values_mut()
+            .for_each(|s| *s = BTreeSet::from([Source::Cache]));
+    }
+
+    pub fn dirs_from(&self, source: Source) -> Vec<String> {
+        self.dirs
+            .iter()
+            .filter(|dir| self.sources.get(*dir).is_some_and(|s| s.contains(&source)))
+            .cloned()
+            .collect()
+    }
+
+    // Only dirs that no untagged source lists are tagged, e.g. a project known to zoxide isn't
+    fn tag(&self, dir: &str) -> Option<&'static str> {
+        let sources = self.sources.get(dir)?;
+        if sources.iter().any(|s| s.tag().is_none()) {
+            return None;
+        }
+        sources.last().and_then(Source::tag)
+    }
+
+    fn sort(&mut self) {
+        // Keep worktrees grouped with their main repo
+        let worktrees = &self.worktrees;
+        let sort_key = |dir: &String| match worktrees.get(dir) {
+            Some(wt) => (wt.repo.clone(), false, dir.clone()),
+            None => (dir.clone(), true, dir.clone()),
+        };
+        self.dirs.sort_by_key(|dir| std::cmp::Reverse(sort_key(dir)));
         self.cursor = self.dirs.len().saturating_sub(1);
         self.filter();
     }
 
+    // Takes effect once the dirs are added
+    pub fn add_worktrees(&mut self, worktrees: Vec<Worktree>) {
+        self.worktrees
+            .extend(worktrees.into_iter().map(|wt| (wt.path.clone(), wt)));
+    }
+
+    pub fn dirs(&self) -> &[String] {
+        &self.dirs
+    }
+
+    // Session name to use instead of the directory name, if any
+    pub fn session_name(&self, dir: &str) -> Option<String> {
+        self.worktrees.get(dir).map(|wt| wt.session_name())
+    }
+
+    pub fn set_boosts(&mut self, boosts: HashMap<String, u32>) {
+        self.boosts = boosts;
+        self.filter();
+    }
+
     pub fn handle_up(&mut self) {
         if self.cursor > 0 {
             self.cursor -= 1;
@@ -47,24 +125,44 @@ impl DirList {
 
     pub fn get_selected(&self) -> Option<String> {
         if self.cursor < self.filtered_dirs.len() {
-            Some(self.filtered_dirs[self.cursor].clone())
+            Some(self.filtered_dirs[self.cursor].item.clone())
         } else {
             None
         }
     }
 
+    pub fn toggle_mark_selected(&mut self) {
+        if let Some(selected) = self.get_selected() {
+            self.marks.toggle(selected);
+        }
+    }
+
+    pub fn mark_all_filtered(&mut self) {
+        self.marks.mark_all(self.filtered_dirs.iter().map(|m| m.item.clone()));
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marks.clear();
+    }
+
+    // Marked entries in list order
+    pub fn marked(&self) -> Vec<String> {
+        self.marks.in_order(&self.dirs)
+    }
+
     pub fn set_search_term(&mut self, search_term: &str) {
         self.search_term = search_term.to_string();
         self.filter();
     }
 
     pub fn filter(&mut self) {
-        self.filtered_dirs = filter::fuzzy_filter(&self.dirs, self.search_term.as_str());
+        self.filtered_dirs =
+            filter::ranked_fuzzy_filter(&self.dirs, self.search_term.as_str(), &self.boosts);
         self.cursor = self.filtered_dirs.len().saturating_sub(1);
     }
 
 
-    pub fn render(&self, rows: usize, _cols: usize) {
+    pub fn render(&self, rows: usize, cols: usize) {
         let from = self.cursor.saturating_sub(rows.saturating_sub(1) / 2).min(self.filtered_dirs.len().saturating_sub(rows));
         let missing_rows = rows.saturating_sub(self.filtered_dirs.len());
         if missing_rows > 0 {
@@ -78,12 +176,31 @@ impl DirList {
             .skip(from)
             .take(rows)
             .for_each(|(i, dir)| {
-                let text = dir.to_string();
+                let (text, indices) = match self.worktrees.get(&dir.item) {
+                    Some(wt) => {
+                        let offset = WORKTREE_INDENT.chars().count();
+                        (
+                            format!("{WORKTREE_INDENT}{} [{}]", dir.item, wt.branch),
+                            dir.indices.iter().map(|i| i + offset).collect(),
+                        )
+                    }
+                    None => (dir.item.to_string(), dir.indices.clone()),
+                };
+                let text = match self.tag(&dir.item) {
+                    Some(tag) => format!("{text} ({tag})"),
+                    None => text,
+                };
+                let marked = self.marks.contains(&dir.item);
+                let mark = self.marks.prefix(&dir.item);
+                let text = format!("{mark}{text}");
+                let indices = indices.iter().map(|i| i + mark.len()).collect();
+                let text: String = text.chars().take(cols).collect();
                 let text_len = text.len();
-                let item = Text::new(text);
-                let item = match i == self.cursor {
-                    true => item.color_range(0, 0..text_len).selected(),
-                    false => item,
+                let item = Text::new(text).color_indices(3, indices);
+                let item = match (i == self.cursor, marked) {
+                    (true, _) => item.color_range(0, 0..text_len).selected(),
+                    (false, true) => item.color_range(1, 0..text_len),
+                    (false, false) => item,
                 };
                 print_text(item);
                 println!();
@@ -91,3 +208,28 @@ impl DirList {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        let mut dirlist = DirList::default();
+        let dirs = ["/home/me/api", "/home/me/web", "/home/me/app", "/home/me/docs"];
+        dirlist.add_dirs(dirs.iter().map(|d| d.to_string()).collect(), Source::Scan);
+        dirlist.toggle_mark_selected();
+        let selected = dirlist.get_selected().unwrap();
+        assert_eq!(dirlist.marked(), vec![selected.clone()]);
+        dirlist.toggle_mark_selected();
+        assert!(dirlist.marked().is_empty());
+
+        // Only the dirs matching the search are marked, and returned in list order
+        dirlist.set_search_term("/home/me/a");
+        dirlist.mark_all_filtered();
+        dirlist.set_search_term("");
+        assert_eq!(dirlist.marked(), vec!["/home/me/app", "/home/me/api"]);
+        dirlist.clear_marks();
+        assert!(dirlist.marked().is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    // Plugin data that couldn't be written to the data dir
+    Save(&'static str, io::Error),
+    UnsetVariable { path: String, var: String },
+    UnclosedVariable(String),
+    // A root dir that isn't mounted in the plugin's filesystem
+    Unreachable { path: PathBuf, cwd: PathBuf },
+    // A data_dir that isn't mounted, the temp dir is used instead
+    UnreachableDataDir { path: PathBuf, cwd: PathBuf },
+    // A root dir that doesn't exist, e.g. a typo
+    Missing { path: PathBuf },
+    // A path without a usable name, ex: "/" or "/.."
+    NoName(PathBuf),
+    Command { command: String, stderr: String },
+    // A setting that can't be acted on without the permissions, ex: open_dir
+    PermissionsDenied(&'static str),
+    // A bind_* setting with a key that doesn't parse
+    InvalidKey { setting: String, key: String },
+    // A setting whose value doesn't parse, its default is used
+    InvalidValue { setting: &'static str, value: String },
+    // A key bound to two actions, the second one keeps it
+    KeyConflict { key: String, actions: (&'static str, &'static str) },
+}
+
+impl Error {
+    /// Warnings come from the configuration, the plugin keeps working around them
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            Error::UnsetVariable { .. } | Error::UnclosedVariable(_)
+                | Error::Unreachable { .. }
+                | Error::UnreachableDataDir { .. }
+                | Error::Missing { .. }
+                | Error::InvalidKey { .. }
+                | Error::InvalidValue { .. }
+                | Error::KeyConflict { .. }
+        )
+    }
+
+    /// A one line description for the status line
+    pub fn summary(&self) -> String {
+        match self {
+            Error::Save(data, _) => format!("Couldn't save the {data}"),
+            Error::UnsetVariable { path, var } => format!("{path}: ${var} is not set"),
+            Error::UnclosedVariable(path) => format!("{path}: unclosed ${{"),
+            Error::Unreachable { path, .. } => format!("{}: can't be scanned", path.display()),
+            Error::UnreachableDataDir { path, .. } => {
+                format!("{}: data_dir can't be written to", path.display())
+            }
+            Error::Missing { path } => format!("{}: no such directory", path.display()),
+            Error::NoName(path) => format!("{}: no session name", path.display()),
+            Error::Command { command, .. } => format!("`{command}` failed"),
+            Error::PermissionsDenied(setting) => {
+                format!("{setting}: the plugin's permissions were denied")
+            }
+            Error::InvalidKey { setting, key } => format!("{setting}: `{key}` is not a key"),
+            Error::InvalidValue { setting, value } => {
+                format!("{setting}: `{value}` is not valid, the default is used")
+            }
+            Error::KeyConflict { key, actions: (_, action) } => {
+                format!("{key}: bound to several actions, {action} is used")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Save(data, source) => write!(f, "Couldn't save the {data}: {source}"),
+            Error::Unreachable { path, cwd } => write!(
+                f,
+                "{}: not below the plugin cwd {}, it can't be scanned",
+                path.display(),
+                cwd.display()
+            ),
+            Error::UnreachableDataDir { path, cwd } => write!(
+                f,
+                "{}: data_dir is not below the plugin cwd {}, the data is kept in zellij's temp dir",
+                path.display(),
+                cwd.display()
+            ),
+            Error::NoName(path) => write!(
+                f,
+                "{}: a session name can't be made from this path",
+                path.display()
+            ),
+            Error::Command { command, stderr } => {
+                write!(f, "`{command}` failed: {}", stderr.trim())
+            }
+            Error::KeyConflict { key, actions: (previous, action) } => write!(
+                f,
+                "{key}: bound to both bind_{previous} and bind_{action}, {action} is used"
+            ),
+            _ => write!(f, "{}", self.summary()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
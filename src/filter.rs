@@ -1,22 +1,72 @@
+use std::collections::HashMap;
+
 use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
-use nucleo_matcher::{Config, Matcher};
+use nucleo_matcher::{Config, Matcher, Utf32Str};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterMatch {
+    pub item: String,
+    pub score: u32,
+    // Char positions of the matched characters in `item`, sorted and deduplicated
+    pub indices: Vec<usize>,
+}
+
+// Boosts are scaled to this range, a full boost adds a quarter to the match score
+pub const MAX_BOOST: u32 = 100;
 
-pub fn fuzzy_filter(items: &[String], search_term: &str) -> Vec<String> {
+/// Scales boosts to 0..=MAX_BOOST relative to the highest one
+pub fn normalize_boosts(boosts: HashMap<String, u32>) -> HashMap<String, u32> {
+    let max = boosts.values().copied().max().unwrap_or(0).max(1) as u64;
+    boosts
+        .into_iter()
+        .map(|(item, boost)| (item, (boost as u64 * MAX_BOOST as u64 / max) as u32))
+        .collect()
+}
+
+pub fn fuzzy_filter(items: &[String], search_term: &str) -> Vec<FilterMatch> {
+    ranked_fuzzy_filter(items, search_term, &HashMap::new())
+}
+
+/// Like `fuzzy_filter`, but raises the match score of each item by `boosts[item]`,
+/// capped at MAX_BOOST, so a boost reorders close matches without beating a much
+/// better one. With an empty search term, items are ordered by their boost alone.
+pub fn ranked_fuzzy_filter(
+    items: &[String],
+    search_term: &str,
+    boosts: &HashMap<String, u32>,
+) -> Vec<FilterMatch> {
+    let boost = |item: &str| boosts.get(item).copied().unwrap_or(0).min(MAX_BOOST);
     if search_term.is_empty() {
-        let sorted = items
+        let mut sorted = items
             .iter()
-            .map(|item| item.to_string())
+            .map(|item| FilterMatch {
+                item: item.to_string(),
+                score: boost(item),
+                ..Default::default()
+            })
             .collect::<Vec<_>>();
+        sorted.sort_by_key(|m| m.score);
         return sorted;
     }
     let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
-    let mut matches = Pattern::parse(search_term, CaseMatching::Ignore, Normalization::Smart)
-        .match_list(items, &mut matcher);
-    matches.sort_by(|a, b| a.1.cmp(&b.1));
+    let pattern = Pattern::parse(search_term, CaseMatching::Ignore, Normalization::Smart);
+    let mut buf = Vec::new();
+    let mut matches: Vec<FilterMatch> = items
+        .iter()
+        .filter_map(|item| {
+            let mut indices = Vec::new();
+            let score = pattern.indices(Utf32Str::new(item, &mut buf), &mut matcher, &mut indices)?;
+            indices.sort_unstable();
+            indices.dedup();
+            Some(FilterMatch {
+                item: item.to_string(),
+                score: score + score * boost(item) / (4 * MAX_BOOST),
+                indices: indices.into_iter().map(|i| i as usize).collect(),
+            })
+        })
+        .collect();
+    matches.sort_by_key(|m| m.score);
     matches
-        .into_iter()
-        .map(|(item, _)| item.to_string())
-        .collect()
 }
 
 #[cfg(test)]
@@ -35,6 +85,44 @@ mod tests {
         .collect();
         let search_term = "bio";
         let result = fuzzy_filter(&items, search_term);
-        assert_eq!(result, vec!["/home/laperlej/Projects/bioblend"]);
+        // nucleo's path matching finds "bio" in the other two paths as well
+        // (b..i..o), so only the order is checked: the best match is rendered last
+        assert_eq!(result.last().unwrap().item, "/home/laperlej/Projects/bioblend");
+    }
+
+    #[test]
+    fn test_fuzzy_filter_indices() {
+        let items = vec!["/home/laperlej/Projects/bioblend".to_string()];
+        let result = fuzzy_filter(&items, "bio");
+        assert_eq!(result[0].indices, vec![24, 25, 26]);
+    }
+
+    #[test]
+    fn test_ranked_fuzzy_filter_empty_search() {
+        let items: Vec<String> = vec!["/b", "/a", "/c"]
+            .into_iter()
+            .map(|item| item.to_string())
+            .collect();
+        let boosts = HashMap::from([("/a".to_string(), 5), ("/c".to_string(), 1)]);
+        let result: Vec<String> = ranked_fuzzy_filter(&items, "", &boosts)
+            .into_iter()
+            .map(|m| m.item)
+            .collect();
+        assert_eq!(result, vec!["/b", "/c", "/a"]);
+    }
+
+    #[test]
+    fn test_boost_does_not_beat_a_better_match() {
+        let items: Vec<String> = vec!["/src/api", "/src/archive/people/index"]
+            .into_iter()
+            .map(|item| item.to_string())
+            .collect();
+        let boosts = normalize_boosts(HashMap::from([
+            ("/src/archive/people/index".to_string(), 5000),
+            ("/src/api".to_string(), 10),
+        ]));
+        assert_eq!(boosts["/src/archive/people/index"], MAX_BOOST);
+        let result = ranked_fuzzy_filter(&items, "api", &boosts);
+        assert_eq!(result.last().unwrap().item, "/src/api");
     }
 }
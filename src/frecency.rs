@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::store::{self, DataFile};
+
+const DATA_FILE: &str = "frecency";
+// Once the summed counts exceed this, every entry is aged down
+const MAX_TOTAL_COUNT: f64 = 1000.0;
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    count: f64,
+    last_access: u64, // seconds since the unix epoch
+}
+
+#[derive(Debug)]
+pub struct Frecency {
+    file: DataFile,
+    entries: HashMap<String, Entry>,
+}
+
+impl Default for Frecency {
+    fn default() -> Self {
+        Self {
+            file: DataFile::new(Path::new(store::TMP_DIR), DATA_FILE),
+            entries: HashMap::new(),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Frecency {
+    /// Loads the entries kept in `dir`, where they are saved from then on
+    pub fn load(&mut self, dir: &Path) {
+        self.file = DataFile::new(dir, DATA_FILE);
+        self.entries = self.file.read().map(|content| parse(&content)).unwrap_or_default();
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.file.write(&serialize(&self.entries))
+    }
+
+    pub fn record(&mut self, dir: &Path) {
+        self.record_at(&dir.to_string_lossy(), now());
+    }
+
+    fn record_at(&mut self, dir: &str, timestamp: u64) {
+        let entry = self.entries.entry(dir.to_string()).or_insert(Entry {
+            count: 0.0,
+            last_access: timestamp,
+        });
+        entry.count += 1.0;
+        entry.last_access = timestamp;
+
+        let total: f64 = self.entries.values().map(|e| e.count).sum();
+        if total > MAX_TOTAL_COUNT {
+            self.entries.values_mut().for_each(|e| e.count *= 0.9);
+            self.entries.retain(|_, e| e.count >= 1.0);
+        }
+    }
+
+    /// Ranking boost per directory, higher is more frecent.
+    pub fn scores(&self) -> HashMap<String, u32> {
+        self.scores_at(now())
+    }
+
+    fn scores_at(&self, timestamp: u64) -> HashMap<String, u32> {
+        self.entries
+            .iter()
+            .map(|(dir, entry)| {
+                let age = timestamp.saturating_sub(entry.last_access);
+                let weight = match age {
+                    a if a < HOUR => 4.0,
+                    a if a < DAY => 2.0,
+                    a if a < WEEK => 0.5,
+                    _ => 0.25,
+                };
+                (dir.clone(), (entry.count * weight).round() as u32)
+            })
+            .collect()
+    }
+}
+
+// One entry per line: "<count>\t<last_access>\t<dir>"
+fn parse(content: &str) -> HashMap<String, Entry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let count = fields.next()?.parse().ok()?;
+            let last_access = fields.next()?.parse().ok()?;
+            let dir = fields.next()?;
+            Some((dir.to_string(), Entry { count, last_access }))
+        })
+        .collect()
+}
+
+fn serialize(entries: &HashMap<String, Entry>) -> String {
+    entries
+        .iter()
+        .map(|(dir, e)| format!("{}\t{}\t{}\n", e.count, e.last_access, dir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_and_frequent_rank_higher() {
+        let mut frecency = Frecency::default();
+        let t = 10 * WEEK;
+        frecency.record_at("/old", t - 2 * WEEK);
+        frecency.record_at("/old", t - 2 * WEEK);
+        frecency.record_at("/recent", t - 10);
+        let scores = frecency.scores_at(t);
+        assert!(scores["/recent"] > scores["/old"]);
+
+        let entries = parse(&serialize(&frecency.entries));
+        assert_eq!(entries, frecency.entries);
+    }
+}
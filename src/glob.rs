@@ -0,0 +1,46 @@
+/// Matches `text` against a glob pattern: `*` matches within a path
+/// component, `**` across components and `?` any single char. Patterns
+/// without a leading `/` may match any trailing part of a path.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern.first() == Some(&'/') {
+        return match_from(&pattern, &text);
+    }
+    // Try every suffix that starts at a component boundary
+    (0..=text.len())
+        .filter(|&i| i == 0 || text[i - 1] == '/')
+        .any(|i| match_from(&pattern, &text[i..]))
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+            (0..=text.len()).any(|i| match_from(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => {
+            let component_end = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+            (0..=component_end).any(|i| match_from(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => !text.is_empty() && text[0] != '/' && match_from(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && match_from(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*/rust-*", "/home/me/rust-parser"));
+        assert!(!glob_match("*/rust-*", "/home/me/web-app"));
+        assert!(glob_match("*.sln", "App.sln"));
+        assert!(!glob_match("/home/*", "/home/me/api"));
+        assert!(glob_match("/home/**/api", "/home/me/work/api"));
+        assert!(glob_match("node_modules", "/home/me/app/node_modules"));
+        assert!(glob_match("ap?", "api"));
+    }
+}
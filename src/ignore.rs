@@ -0,0 +1,57 @@
+use crate::glob::glob_match;
+
+/// A list of gitignore-style patterns: a pattern without a `/` matches a
+/// directory name anywhere, one with a `/` matches the end of the path unless
+/// it starts with `/`, a trailing `/` is ignored since only directories are
+/// scanned and a leading `!` re-includes what an earlier pattern matched.
+/// The last matching pattern wins. Unlike gitignore, patterns are matched
+/// against absolute paths since there is no file to anchor them to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IgnoreRules {
+    rules: Vec<(String, bool)>, // (pattern, negated)
+}
+
+impl From<&str> for IgnoreRules {
+    // "node_modules;archive-*/;!archive-2024"
+    fn from(rules: &str) -> Self {
+        let rules = rules
+            .split(';')
+            .map(|s| s.trim().trim_end_matches('/'))
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.strip_prefix('!') {
+                Some(pattern) => (pattern.to_string(), true),
+                None => (s.to_string(), false),
+            })
+            .collect();
+        Self { rules }
+    }
+}
+
+impl IgnoreRules {
+    pub fn is_match(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| glob_match(pattern, path))
+            .map(|(_, negated)| !negated)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        let rules = IgnoreRules::from("node_modules;archive-*/;!archive-2024;/home/me/tmp*;old/web");
+        assert!(rules.is_match("/home/me/web/node_modules"));
+        assert!(rules.is_match("/home/me/archive-2023"));
+        assert!(!rules.is_match("/home/me/archive-2024"));
+        assert!(rules.is_match("/home/me/old/web"));
+        assert!(!rules.is_match("/home/me/web"));
+        assert!(rules.is_match("/home/me/tmp2"));
+        assert!(!rules.is_match("/home/me/api"));
+        assert!(!IgnoreRules::default().is_match("/home/me/api"));
+    }
+}
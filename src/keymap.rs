@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use zellij_tile::prelude::*;
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Next,
+    Previous,
+    SwitchScreen,
+    Accept,
+    OpenTab,
+    OpenPane,
+    Close,
+    Kill,
+    ToggleMark,
+    MarkAll,
+    ClearMarks,
+    Rename,
+    DisconnectOthers,
+    TogglePreview,
+    ToggleErrors,
+}
+
+impl Action {
+    const ALL: [Action; 15] = [
+        Action::Next,
+        Action::Previous,
+        Action::SwitchScreen,
+        Action::Accept,
+        Action::OpenTab,
+        Action::OpenPane,
+        Action::Close,
+        Action::Kill,
+        Action::ToggleMark,
+        Action::MarkAll,
+        Action::ClearMarks,
+        Action::Rename,
+        Action::DisconnectOthers,
+        Action::TogglePreview,
+        Action::ToggleErrors,
+    ];
+
+    // Name of the configuration key, prefixed with "bind_"
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Next => "next",
+            Action::Previous => "previous",
+            Action::SwitchScreen => "switch_screen",
+            Action::Accept => "accept",
+            Action::OpenTab => "open_tab",
+            Action::OpenPane => "open_pane",
+            Action::Close => "close",
+            Action::Kill => "kill",
+            Action::ToggleMark => "toggle_mark",
+            Action::MarkAll => "mark_all",
+            Action::ClearMarks => "clear_marks",
+            Action::Rename => "rename",
+            Action::DisconnectOthers => "disconnect_others",
+            Action::TogglePreview => "toggle_preview",
+            Action::ToggleErrors => "toggle_errors",
+        }
+    }
+
+    fn default_keys(&self) -> &'static str {
+        match self {
+            Action::Next => "Ctrl n;Down",
+            Action::Previous => "Ctrl p;Up",
+            Action::SwitchScreen => "Tab",
+            Action::Accept => "Enter",
+            Action::OpenTab => "Ctrl t",
+            Action::OpenPane => "Alt Enter",
+            Action::Close => "Esc",
+            Action::Kill => "Ctrl x",
+            Action::ToggleMark => "Ctrl s",
+            Action::MarkAll => "Alt a",
+            Action::ClearMarks => "Alt c",
+            Action::Rename => "Ctrl r",
+            Action::DisconnectOthers => "Ctrl o",
+            Action::TogglePreview => "Ctrl v",
+            Action::ToggleErrors => "Alt e",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<KeyWithModifier, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::from_config(&BTreeMap::new()).0
+    }
+}
+
+impl Keymap {
+    /// "bind_next" "Ctrl j;Down" replaces the default keys of the action. Keys that
+    /// don't parse and keys bound to several actions are returned as warnings, the
+    /// action listed last in `Action::ALL` keeps such a key.
+    pub fn from_config(config: &BTreeMap<String, String>) -> (Self, Vec<Error>) {
+        let mut bindings = HashMap::new();
+        let mut warnings = vec![];
+        for action in Action::ALL {
+            let setting = format!("bind_{}", action.name());
+            let (keys, invalid) = match config.get(&setting) {
+                Some(keys) => parse_keys(&setting, keys),
+                _ => parse_keys(&setting, action.default_keys()),
+            };
+            warnings.extend(invalid);
+            for key in keys {
+                if let Some(previous) = bindings.insert(key.clone(), action) {
+                    warnings.push(Error::KeyConflict {
+                        key: key.to_string(),
+                        actions: (previous.name(), action.name()),
+                    });
+                }
+            }
+        }
+        (Self { bindings }, warnings)
+    }
+
+    pub fn get(&self, key: &KeyWithModifier) -> Option<Action> {
+        self.bindings.get(key).copied()
+    }
+
+    // A key bound to the action, for hints
+    pub fn key_for(&self, action: Action) -> Option<&KeyWithModifier> {
+        let mut keys: Vec<&KeyWithModifier> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort_by_key(|key| key.to_string());
+        keys.first().copied()
+    }
+}
+
+// The keys that don't parse are returned as warnings
+fn parse_keys(setting: &str, keys: &str) -> (Vec<KeyWithModifier>, Vec<Error>) {
+    let mut warnings = vec![];
+    let keys = keys
+        .split(';')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .filter_map(|key| match KeyWithModifier::from_str(key) {
+            Ok(key) => Some(key),
+            Err(_) => {
+                warnings.push(Error::InvalidKey {
+                    setting: setting.to_string(),
+                    key: key.to_string(),
+                });
+                None
+            }
+        })
+        .collect();
+    (keys, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_from_config() {
+        let config = BTreeMap::from([
+            ("bind_next".to_string(), "Ctrl j;Down".to_string()),
+            ("bind_kill".to_string(), "Alt d".to_string()),
+        ]);
+        let (keymap, warnings) = Keymap::from_config(&config);
+        assert!(warnings.is_empty());
+        let ctrl = |c| KeyWithModifier::new(BareKey::Char(c)).with_ctrl_modifier();
+        assert_eq!(keymap.get(&ctrl('j')), Some(Action::Next));
+        assert_eq!(keymap.get(&KeyWithModifier::new(BareKey::Down)), Some(Action::Next));
+        assert_eq!(keymap.get(&ctrl('n')), None);
+        assert_eq!(keymap.get(&ctrl('p')), Some(Action::Previous));
+        let alt_d = KeyWithModifier::new(BareKey::Char('d')).with_alt_modifier();
+        assert_eq!(keymap.get(&alt_d), Some(Action::Kill));
+        assert_eq!(keymap.get(&ctrl('x')), None);
+    }
+
+    #[test]
+    fn test_keymap_warnings() {
+        let config = BTreeMap::from([
+            ("bind_next".to_string(), "Ctrl-j".to_string()),
+            ("bind_kill".to_string(), "Ctrl p".to_string()),
+        ]);
+        let (keymap, warnings) = Keymap::from_config(&config);
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(&warnings[0], Error::InvalidKey { key, .. } if key == "Ctrl-j"));
+        assert!(matches!(warnings[1], Error::KeyConflict { actions: ("previous", "kill"), .. }));
+        let ctrl_p = KeyWithModifier::new(BareKey::Char('p')).with_ctrl_modifier();
+        assert_eq!(keymap.get(&ctrl_p), Some(Action::Kill));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use kdl::{KdlDocument, KdlNode};
+use zellij_tile::prelude::*;
+
+use crate::config::Config;
+use crate::glob::glob_match;
+use crate::preview::DIR_KEY;
+use crate::COMMAND_KEY;
+
+pub const LAYOUT_DUMP: &str = "layout_dump";
+
+// Layout files looked up in the project dir, in order
+const PROJECT_LAYOUTS: [&str; 2] = ["layout.kdl", ".zellij/layout.kdl"];
+
+/// Where the layout of a directory came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutSource {
+    Project(String),
+    RootDir(PathBuf),
+    Rule(String),
+    Default,
+}
+
+/// Resolves the layout for `dir`, `host_dir` is where it is mounted in the
+/// plugin's filesystem. The first match wins: the project's own layout files,
+/// the layout of the root dir it was found in, the first matching glob rule
+/// and finally the configured session layout. Project layout files are looked
+/// up through `host_dir` but returned with their host path, for zellij.
+pub fn resolve(dir: &Path, host_dir: Option<&Path>, config: &Config) -> (LayoutInfo, LayoutSource) {
+    if let Some(host_dir) = host_dir {
+        for file in PROJECT_LAYOUTS {
+            if host_dir.join(file).exists() {
+                let layout = LayoutInfo::File(dir.join(file).to_string_lossy().to_string());
+                return (layout, LayoutSource::Project(file.to_string()));
+            }
+        }
+    }
+    let root_layout = config
+        .root_layouts
+        .iter()
+        .filter(|(root, _)| dir.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count());
+    if let Some((root, layout)) = root_layout {
+        return (layout.clone(), LayoutSource::RootDir(root.clone()));
+    }
+    let dir_str = dir.to_string_lossy();
+    if let Some((pattern, layout)) = config
+        .layout_rules
+        .iter()
+        .find(|(pattern, _)| glob_match(pattern, &dir_str))
+    {
+        return (layout.clone(), LayoutSource::Rule(pattern.clone()));
+    }
+    (config.layout.clone(), LayoutSource::Default)
+}
+
+// ex: ":rust (rule */rust-*)"
+pub fn describe(layout: &LayoutInfo, source: &LayoutSource) -> String {
+    let name = match layout {
+        LayoutInfo::BuiltIn(name) => format!(":{name}"),
+        LayoutInfo::File(path) | LayoutInfo::Url(path) => path.to_string(),
+        LayoutInfo::Stringified(_) => "custom".to_string(),
+    };
+    match source {
+        LayoutSource::Project(file) => format!("{file} (project)"),
+        LayoutSource::RootDir(root) => format!("{name} (root dir {})", root.display()),
+        LayoutSource::Rule(pattern) => format!("{name} (rule {pattern})"),
+        LayoutSource::Default => format!("{name} (default)"),
+    }
+}
+
+pub fn is_template(layout: &str) -> bool {
+    layout.contains("{{")
+}
+
+/// Replaces every `{{name}}` in the layout with its value, values are
+/// escaped so they can be used inside KDL strings.
+pub fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    vars.iter().fold(template.to_string(), |layout, (name, value)| {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        layout.replace(&format!("{{{{{name}}}}}"), &escaped)
+    })
+}
+
+fn kdl_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A single pane tab, used when the layout to open isn't available as KDL.
+pub fn tab_layout(name: &str, dir: &Path) -> String {
+    format!(
+        "layout {{\n    tab name={} cwd={} {{\n        pane\n    }}\n}}\n",
+        kdl_string(name),
+        kdl_string(&dir.to_string_lossy())
+    )
+}
+
+/// Asks zellij for the KDL of a layout the plugin can't read, e.g. a built-in
+/// one or a file outside of its cwd. Returns false for layouts it can't dump.
+pub fn request_dump(layout: &LayoutInfo, dir: &Path) -> bool {
+    let name = match layout {
+        LayoutInfo::BuiltIn(name) | LayoutInfo::File(name) => name,
+        _ => return false,
+    };
+    let context = BTreeMap::from([
+        (COMMAND_KEY.to_string(), LAYOUT_DUMP.to_string()),
+        (DIR_KEY.to_string(), dir.to_string_lossy().to_string()),
+    ]);
+    run_command(&["zellij", "setup", "--dump-layout", name], context);
+    true
+}
+
+/// Sets `dir` as the base cwd of the layout, unless it already sets one.
+/// Layouts that aren't valid KDL are returned as is.
+pub fn with_cwd(layout: &str, dir: &Path) -> String {
+    let Ok(mut doc) = layout.parse::<KdlDocument>() else {
+        return layout.to_string();
+    };
+    let Some(children) = doc.get_mut("layout").map(KdlNode::ensure_children) else {
+        return layout.to_string();
+    };
+    if children.get("cwd").is_some() {
+        return layout.to_string();
+    }
+    let mut cwd = KdlNode::new("cwd");
+    cwd.push(dir.to_string_lossy().to_string());
+    // Terminated explicitly, the next node may be on the same line
+    cwd.set_leading("\n    ");
+    cwd.set_trailing(";");
+    children.nodes_mut().insert(0, cwd);
+    doc.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_cwd() {
+        let layout = "layout {\n    pane\n}\n";
+        assert_eq!(
+            with_cwd(layout, Path::new("/home/me/api")),
+            "layout {\n    cwd \"/home/me/api\";\n    pane\n}\n"
+        );
+        assert_eq!(
+            with_cwd("layout {}", Path::new("/home/me/api")),
+            "layout {\n    cwd \"/home/me/api\";}"
+        );
+        let layout = "layout {\n    cwd \"/tmp\"\n}\n";
+        assert_eq!(with_cwd(layout, Path::new("/home/me/api")), layout);
+        // Only a cwd of the layout itself counts, not one of a pane or in a comment
+        let layout = "// no layout cwd\nlayout {\n    pane cwd=\"src\"\n}\n";
+        assert_eq!(
+            with_cwd(layout, Path::new("/home/me/api")),
+            "// no layout cwd\nlayout {\n    cwd \"/home/me/api\";\n    pane cwd=\"src\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = "layout {\n    tab name=\"{{project_name}}\" cwd=\"{{project_dir}}\"\n}\n";
+        let layout = render_template(
+            template,
+            &[("project_dir", "/home/me/api"), ("project_name", "api")],
+        );
+        assert_eq!(layout, "layout {\n    tab name=\"api\" cwd=\"/home/me/api\"\n}\n");
+    }
+
+    #[test]
+    fn test_resolve_layout() {
+        let config = Config::from(std::collections::BTreeMap::from([
+            ("root_layouts".to_string(), "/home/me/work=:compact".to_string()),
+            ("layout_rules".to_string(), "*/rust-*=:rust;*/web-*=web".to_string()),
+        ]));
+        let resolve = |dir: &str| resolve(Path::new(dir), None, &config);
+        assert_eq!(
+            resolve("/home/me/work/rust-api"),
+            (
+                LayoutInfo::BuiltIn("compact".to_string()),
+                LayoutSource::RootDir(PathBuf::from("/home/me/work"))
+            )
+        );
+        assert_eq!(
+            resolve("/home/me/oss/rust-api"),
+            (
+                LayoutInfo::BuiltIn("rust".to_string()),
+                LayoutSource::Rule("*/rust-*".to_string())
+            )
+        );
+        assert_eq!(
+            resolve("/home/me/oss/docs"),
+            (LayoutInfo::BuiltIn("default".to_string()), LayoutSource::Default)
+        );
+    }
+
+    #[test]
+    fn test_project_layout_has_its_host_path() {
+        let host_dir = std::env::temp_dir().join(format!("sessionizer-layout-{}", std::process::id()));
+        std::fs::create_dir_all(host_dir.join(".zellij")).unwrap();
+        std::fs::write(host_dir.join(".zellij/layout.kdl"), "layout {}").unwrap();
+        let resolved = resolve(Path::new("/home/me/api"), Some(&host_dir), &Config::default());
+        std::fs::remove_dir_all(&host_dir).unwrap();
+        assert_eq!(
+            resolved,
+            (
+                LayoutInfo::File("/home/me/api/.zellij/layout.kdl".to_string()),
+                LayoutSource::Project(".zellij/layout.kdl".to_string())
+            )
+        );
+    }
+}
@@ -1,31 +1,77 @@
+// The plugin isn't registered in native test builds, which leaves most of it unused there
+#![cfg_attr(test, allow(dead_code))]
+
 use zellij_tile::prelude::*;
 
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::path::PathBuf;
 
+use cache::ScanCache;
 use config::Config;
+use sources::{Context, DirSource, Found, Source};
 
+mod cache;
 mod config;
 mod dirlist;
+mod error;
 mod filter;
+mod frecency;
+mod glob;
+mod ignore;
+mod keymap;
+mod layout;
+mod markers;
+mod marks;
+mod naming;
+mod paths;
+mod pipe;
+mod preview;
+mod prompt;
+mod scan;
+mod sessiondirs;
 mod sesslist;
+mod sources;
+mod store;
+#[cfg(test)]
+mod test_support;
 mod textinput;
+mod unifiedlist;
+mod worktree;
+mod zoxide;
 use dirlist::DirList;
+use error::Error;
+use frecency::Frecency;
+use keymap::Action;
+use pipe::PipeCommand;
+use preview::Preview;
+use prompt::{Prompt, SessionAction};
+use sessiondirs::SessionDirs;
 use sesslist::{SessList, Session};
 use textinput::TextInput;
+use unifiedlist::{Target, UnifiedList};
 
 const ROOT: &str = "/host";
+// The preview column is hidden when the plugin is narrower than this
+const PREVIEW_MIN_COLS: usize = 100;
+// Context key identifying which command a RunCommandResult belongs to
+const COMMAND_KEY: &str = "command";
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 enum Screen {
+    #[default]
     SearchDirs,
     SearchSessions,
+    Unified,
 }
 
-impl Default for Screen {
-    fn default() -> Self {
-        return Screen::SearchDirs;
+impl Screen {
+    fn next(&self) -> Screen {
+        match self {
+            Screen::SearchDirs => Screen::SearchSessions,
+            Screen::SearchSessions => Screen::Unified,
+            Screen::Unified => Screen::SearchDirs,
+        }
     }
 }
 
@@ -33,40 +79,259 @@ impl Default for Screen {
 struct State {
     dirlist: DirList,
     sesslist: SessList,
+    unifiedlist: UnifiedList,
     cwd: PathBuf,
     textinput: TextInput,
     current_session: String,
     screen: Screen,
-    textinput_dump: String,
+    // Search text of the screens that are not shown
+    textinput_dumps: HashMap<Screen, String>,
+    prompt: Option<Prompt>,
 
     config: Config,
-    debug: String,
-    // Track directories that contain root files
-    valid_dirs: Vec<String>,
-    // Track which directories we're waiting to scan
-    pending_scans: HashSet<PathBuf>,
-    // Track root directories to prevent recursive scanning
-    root_dirs_set: HashSet<PathBuf>,
+    preview: Preview,
+    show_preview: bool,
+    frecency: Frecency,
+    session_dirs: SessionDirs,
+    // Shown in the status line, the latest last
+    errors: Vec<Error>,
+    show_errors: bool,
+    // Waiting for the host environment to expand the config paths
+    resolving_paths: bool,
+    permissions_granted: bool,
+    // Where the listed dirs come from
+    sources: Vec<Box<dyn DirSource>>,
+    // None until the data dir is known
+    cache: Option<ScanCache>,
+    cache_key: String,
+    // Pipe commands received before the plugin could run them, with their CLI pipe
+    pending_pipes: Vec<(PipeCommand, Option<String>)>,
+    // The ranking boosts each source found, e.g. zoxide's scores
+    source_boosts: HashMap<Source, HashMap<String, u32>>,
 }
 
+// Its exports, `pipe` among them, would shadow libc's functions in native test builds
+#[cfg(not(test))]
 register_plugin!(State);
 
 impl State {
-    fn change_root(&mut self, path: &Path) -> PathBuf {
-        self.cwd.join(path.strip_prefix(ROOT).unwrap())
+    // The frecency scores and the boosts of the sources add up, each normalized
+    // so that no source outweighs the others
+    fn boosts(&self) -> HashMap<String, u32> {
+        let mut boosts = filter::normalize_boosts(self.frecency.scores());
+        let source_boosts = self
+            .source_boosts
+            .values()
+            .flat_map(|b| filter::normalize_boosts(b.clone()));
+        for (dir, score) in source_boosts {
+            *boosts.entry(dir).or_default() += score;
+        }
+        boosts
     }
 
-    fn switch_session_with_cwd(&self, dir: &Path) -> Result<(), String> {
-        let session_name = dir.file_name().unwrap().to_str().unwrap();
-        let cwd = dir.to_path_buf();
-        let host_layout_path = PathBuf::from(ROOT)
-            .join(dir.strip_prefix("/").unwrap())
-            .join("layout.kdl");
-        let layout = if host_layout_path.exists() {
-            LayoutInfo::File(host_layout_path.to_str().unwrap().into())
+    fn report(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+
+    fn save_frecency(&mut self) {
+        if let Err(e) = self.frecency.save() {
+            self.report(Error::Save("frecency", e));
+        }
+    }
+
+    fn save_session_dirs(&mut self) {
+        if let Err(e) = self.session_dirs.save() {
+            self.report(Error::Save("session dirs", e));
+        }
+    }
+
+    fn host_path(&self, dir: &Path) -> Option<PathBuf> {
+        paths::to_host(dir, &self.cwd)
+    }
+
+    // The directory or session highlighted on the current screen
+    fn selected_target(&self) -> Option<Target> {
+        match self.screen {
+            Screen::SearchDirs => self.dirlist.get_selected().map(Target::Dir),
+            Screen::SearchSessions => self.sesslist.get_selected().map(Target::Session),
+            Screen::Unified => self.unifiedlist.get_selected().map(|e| e.target),
+        }
+    }
+
+    fn request_preview(&mut self) {
+        if !self.show_preview {
+            return;
+        }
+        if let Some(Target::Dir(dir)) = self.selected_target() {
+            let host_dir = self.host_path(Path::new(&dir));
+            self.preview
+                .request_dir(&dir, host_dir.as_deref(), self.permissions_granted);
+        }
+    }
+
+    fn render_preview(&self, x: usize, y: usize, width: usize, height: usize) {
+        match self.selected_target() {
+            Some(Target::Dir(dir)) => {
+                let (layout, source) = self.resolve_layout(Path::new(&dir));
+                let layout = layout::describe(&layout, &source);
+                self.preview.render_dir(&dir, &layout, x, y, width, height);
+            }
+            Some(Target::Session(name)) => {
+                if let Some(session) = self.sesslist.get_session(&name) {
+                    self.preview.render_session(session, x, y, width, height);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Filters the list of the current screen with the search bar
+    fn apply_search_term(&mut self) {
+        let search_term = self.textinput.get_text();
+        match self.screen {
+            Screen::SearchDirs => self.dirlist.set_search_term(&search_term),
+            Screen::SearchSessions => self.sesslist.set_search_term(&search_term),
+            Screen::Unified => self.unifiedlist.set_search_term(&search_term),
+        }
+    }
+
+    // The latest error, or all of them with their details when expanded
+    fn render_status(&self, cols: usize) {
+        let Some(latest) = self.errors.last() else {
+            return;
+        };
+        let color = |error: &Error| match error.is_warning() {
+            true => 3,
+            false => 0,
+        };
+        if self.show_errors {
+            for error in &self.errors {
+                let line: String = error.to_string().chars().take(cols).collect();
+                print_text(Text::new(line).color_range(color(error), ..));
+                println!();
+            }
+            return;
+        }
+        let hint = match (self.errors.len(), self.config.keymap.key_for(Action::ToggleErrors)) {
+            (1, Some(key)) => format!(" ({key} for details)"),
+            (n, Some(key)) => format!(" (+{} more, {key} for details)", n - 1),
+            (1, None) => String::new(),
+            (n, None) => format!(" (+{} more)", n - 1),
+        };
+        let summary = latest.summary();
+        let width = cols.saturating_sub(hint.chars().count());
+        let summary: String = summary.chars().take(width).collect();
+        let summary_len = summary.chars().count();
+        let status = Text::new(format!("{summary}{hint}"))
+            .color_range(color(latest), 0..summary_len)
+            .color_range(1, summary_len..);
+        print_text(status);
+        println!();
+    }
+
+    // The session highlighted on the sessions or unified screen
+    fn selected_session(&self) -> Option<String> {
+        match self.screen {
+            Screen::SearchDirs => None,
+            Screen::SearchSessions => self.sesslist.get_selected(),
+            Screen::Unified => self.unifiedlist.get_selected().and_then(|e| e.session),
+        }
+    }
+
+    fn run_or_confirm(&mut self, action: SessionAction) {
+        if self.config.confirm_actions {
+            self.prompt = Some(Prompt::Confirm(action));
         } else {
-            self.config.layout.clone()
+            self.run_action(action);
+        }
+    }
+
+    // The marks are kept when the action is cancelled
+    fn run_action(&mut self, action: SessionAction) {
+        action.run();
+        if let SessionAction::Kill { .. } = action {
+            self.sesslist.clear_marks();
+        }
+    }
+
+    // Kills the marked sessions, or the highlighted one when none are marked
+    fn kill_selected(&mut self) {
+        let marked = match self.screen {
+            Screen::SearchSessions => self.sesslist.marked(),
+            _ => vec![],
+        };
+        let sessions = match (marked.is_empty(), self.selected_session()) {
+            (false, _) => marked,
+            (true, Some(session)) => vec![session],
+            (true, None) => return,
+        };
+        self.run_or_confirm(self.sesslist.kill_action(sessions));
+    }
+
+    // Dirs and sessions are acted on differently, so the unified screen has no marks
+    fn notice_no_marks(&mut self) {
+        let notice = "Marks are only available on the directories and sessions screens";
+        self.prompt = Some(Prompt::Notice(notice.to_string()));
+    }
+
+    // Zellij can only rename and disconnect the clients of the current session
+    fn current_session_selected(&mut self, notice: &str) -> bool {
+        match self.selected_session() {
+            Some(session) if session == self.current_session => true,
+            Some(_) => {
+                self.prompt = Some(Prompt::Notice(notice.to_string()));
+                false
+            }
+            None => false,
+        }
+    }
+
+    // Keys go to the prompt while it is shown, a notice is dismissed by any key
+    fn handle_prompt_key(&mut self, prompt: Prompt, key: &KeyWithModifier) {
+        let bare_key = match key.key_modifiers.is_empty() {
+            true => Some(key.bare_key),
+            false => None,
         };
+        match prompt {
+            Prompt::Confirm(action) => match bare_key {
+                Some(BareKey::Char('y')) | Some(BareKey::Enter) => self.run_action(action),
+                Some(BareKey::Char('n')) | Some(BareKey::Esc) => {}
+                _ => self.prompt = Some(Prompt::Confirm(action)),
+            },
+            Prompt::Rename { search_term } => match bare_key {
+                Some(BareKey::Enter) => {
+                    let new_name = self.config.session_naming.sanitize(&self.textinput.get_text());
+                    if !new_name.is_empty() && new_name != self.current_session {
+                        rename_session(&new_name);
+                        self.session_dirs.rename(&self.current_session, &new_name);
+                        self.save_session_dirs();
+                    }
+                    self.textinput.replace_text(&search_term);
+                }
+                Some(BareKey::Esc) => {
+                    self.textinput.replace_text(&search_term);
+                }
+                _ => {
+                    self.textinput.handle_key(key);
+                    self.prompt = Some(Prompt::Rename { search_term });
+                }
+            },
+            Prompt::Notice(_) => {}
+        }
+    }
+
+    fn switch_session_with_cwd(&mut self, dir: &Path) -> Result<(), Error> {
+        let session_name = self.session_name_for(dir);
+        if session_name.is_empty() {
+            return Err(Error::NoName(dir.to_path_buf()));
+        }
+        let session_name = session_name.as_str();
+        self.frecency.record(dir);
+        self.save_frecency();
+        self.session_dirs.insert(session_name, &dir.to_string_lossy());
+        self.save_session_dirs();
+        let cwd = dir.to_path_buf();
+        let layout = self.layout_for(dir);
         // Switch session will panic if the session is the current session
         if session_name != self.current_session {
             switch_session_with_layout(Some(session_name), layout, Some(cwd));
@@ -74,74 +339,395 @@ impl State {
         Ok(())
     }
 
-    fn process_filesystem_update(&mut self, paths: &[(PathBuf, Option<FileMetadata>)]) {
-        // Process directories from root scans
-        let dirs: Vec<PathBuf> = paths
+    // Layout files containing template variables are filled in for the dir.
+    // Files outside the plugin cwd can't be read, their variables are left as is.
+    fn layout_for(&self, dir: &Path) -> LayoutInfo {
+        let layout = self.resolve_layout(dir).0;
+        match self.read_layout(&layout) {
+            Some(content) if layout::is_template(&content) => {
+                let name = dir.file_name().unwrap_or_default().to_string_lossy();
+                let branch = naming::git_branch(dir, &self.cwd).unwrap_or_default();
+                LayoutInfo::Stringified(layout::render_template(
+                    &content,
+                    &[
+                        ("project_dir", &dir.to_string_lossy()),
+                        ("project_name", &name),
+                        ("git_branch", &branch),
+                    ],
+                ))
+            }
+            _ => layout,
+        }
+    }
+
+    // The KDL of a layout, if it is a file we can read
+    fn read_layout(&self, layout: &LayoutInfo) -> Option<String> {
+        match layout {
+            LayoutInfo::File(path) => self
+                .host_path(Path::new(path))
+                .and_then(|p| std::fs::read_to_string(p).ok()),
+            LayoutInfo::Stringified(content) => Some(content.clone()),
+            _ => None,
+        }
+    }
+
+    fn resolve_layout(&self, dir: &Path) -> (LayoutInfo, layout::LayoutSource) {
+        let host_dir = self.host_path(dir);
+        layout::resolve(dir, host_dir.as_deref(), &self.config)
+    }
+
+    // Opens the dir as a new tab of the current session, with the same layout a session would get.
+    // Returns false while waiting for zellij to dump a layout the plugin can't read.
+    fn open_dir_as_tab(&mut self, dir: &Path) -> bool {
+        self.frecency.record(dir);
+        self.save_frecency();
+        let layout = self.layout_for(dir);
+        match self.read_layout(&layout) {
+            Some(content) => open_layout_as_tab(&content, dir),
+            None if layout::request_dump(&layout, dir) => return false,
+            None => open_layout_as_tab("", dir),
+        }
+        true
+    }
+
+    fn open_dir_as_pane(&mut self, dir: &Path) {
+        self.frecency.record(dir);
+        self.save_frecency();
+        open_terminal(dir);
+    }
+
+    // The highlighted dir on the dirs or unified screen
+    fn selected_dir(&self) -> Option<String> {
+        match self.selected_target() {
+            Some(Target::Dir(dir)) => Some(dir),
+            _ => None,
+        }
+    }
+
+    // A dir keeps the name of the session created for it, whichever dirs are listed
+    fn session_name_for(&self, dir: &Path) -> String {
+        let naming = &self.config.session_naming;
+        let dir_str = dir.to_string_lossy();
+        if let Some(name) = self.session_dirs.session_for(&dir_str) {
+            return name.clone();
+        }
+        if let Some(name) = self.dirlist.session_name(&dir_str) {
+            return naming.sanitize(&name);
+        }
+        let branch = match naming.template.contains("{git_branch}") {
+            true => naming::git_branch(dir, &self.cwd),
+            false => None,
+        };
+        // A name already used by the session of another dir forces a longer one
+        let others: Vec<(PathBuf, String)> = self
+            .session_dirs
             .iter()
-            .filter(|(p, _)| p.is_dir() && !is_hidden(p))
-            .map(|(p, _)| p.clone())
+            .map(|(name, dir)| (PathBuf::from(dir), name.clone()))
+            .collect();
+        // So do the live or resurrectable sessions whose dir isn't known, they
+        // may have been created for another dir or outside the plugin
+        let taken: Vec<String> = self
+            .sesslist
+            .sessions()
+            .filter(|s| self.session_dirs.get(&s.name).is_none())
+            .map(|s| s.name.clone())
             .collect();
-            
-        // Check if any of these directories are direct children of our root directories
-        let mut subdirs_to_scan = Vec::new();
-        for dir in &dirs {
-            if let Some(parent) = dir.parent() {
-                if self.root_dirs_set.contains(parent) {
-                    // This is a direct child of a root directory - scan it
-                    subdirs_to_scan.push(dir.clone());
+        naming.disambiguate(naming.render(dir, branch.as_deref()), dir, &others, &taken)
+    }
+
+    // The directory a session was created for, falling back to a dir with the same name
+    fn session_dir(&self, session_name: &str) -> Option<String> {
+        if let Some(dir) = self.session_dirs.get(session_name) {
+            return Some(dir.clone());
+        }
+        let mut matching = self.dirlist.dirs().iter().filter(|d| {
+            Path::new(d).file_name().and_then(|n| n.to_str()) == Some(session_name)
+        });
+        match (matching.next(), matching.next()) {
+            (Some(dir), None) => Some(dir.clone()),
+            _ => None,
+        }
+    }
+
+    fn handle_command_result(
+        &mut self,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        stderr: &[u8],
+        context: &BTreeMap<String, String>,
+    ) -> bool {
+        if context.get(COMMAND_KEY).map(String::as_str) == Some(paths::ENV) {
+            let env = match exit_code {
+                Some(0) => paths::parse_env(&String::from_utf8_lossy(stdout)),
+                _ => {
+                    self.report(Error::Command {
+                        command: "env".to_string(),
+                        stderr: String::from_utf8_lossy(stderr).to_string(),
+                    });
+                    HashMap::new()
                 }
+            };
+            self.resolve_paths(&env);
+            return true;
+        }
+        if context.get(COMMAND_KEY).map(String::as_str) == Some(layout::LAYOUT_DUMP) {
+            if let Some(dir) = context.get(preview::DIR_KEY) {
+                let content = match exit_code {
+                    Some(0) => String::from_utf8_lossy(stdout).to_string(),
+                    _ => String::new(),
+                };
+                open_layout_as_tab(&content, Path::new(dir));
             }
+            close_self();
+            return true;
         }
-        
-        // Scan subdirectories to check for root files
-        for subdir in subdirs_to_scan {
-            self.pending_scans.insert(subdir.clone());
-            scan_host_folder(&subdir);
-        }
-        
-        // Check if we're processing a subdirectory scan (looking for root files)
-        let mut dirs_with_root_files = HashSet::new();
-        let mut processed_pending_dirs = HashSet::new();
-        
-        for (path, _) in paths {
-            if let Some(parent) = path.parent() {
-                if self.pending_scans.contains(parent) {
-                    // This is a scan of a subdirectory we're checking
-                    processed_pending_dirs.insert(parent.to_path_buf());
-                    
-                    if let Some(filename) = path.file_name() {
-                        if let Some(filename_str) = filename.to_str() {
-                            if self.config.root_files.contains(&filename_str.to_string()) {
-                                // Found a root file! The parent directory is valid
-                                dirs_with_root_files.insert(parent.to_path_buf());
-                            }
-                        }
-                    }
+        if exit_code != Some(0) {
+            return false;
+        }
+        match context.get(COMMAND_KEY).map(String::as_str) {
+            Some(preview::GIT_STATUS) => {
+                if let Some(dir) = context.get(preview::DIR_KEY) {
+                    self.preview
+                        .handle_git_status(dir, &String::from_utf8_lossy(stdout));
                 }
+                true
             }
+            _ => false,
         }
-        
-        // Remove all processed pending scans (whether they had root files or not)
-        for dir in &processed_pending_dirs {
-            self.pending_scans.remove(dir);
-        }
-        
-        // Add valid directories to our list
-        for dir in dirs_with_root_files {
-            let display_path = if dir.starts_with(ROOT) {
-                self.change_root(&dir)
-            } else {
-                dir.to_path_buf()
-            };
-            let path_str = display_path.to_string_lossy().to_string();
-            if !self.valid_dirs.contains(&path_str) {
-                self.valid_dirs.push(path_str);
+    }
+
+    // Drop cached directories that the fresh scan didn't find and cache the new results
+    fn finish_scan(&mut self) {
+        self.dirlist.remove_source(Source::Cache);
+        let mut scanned_dirs = self.dirlist.dirs_from(Source::Scan);
+        scanned_dirs.extend(self.dirlist.dirs_from(Source::Worktree));
+        scanned_dirs.sort();
+        scanned_dirs.dedup();
+        let saved = self.cache.as_ref().map(|cache| cache.save(&scanned_dirs));
+        if let Some(Err(e)) = saved {
+            self.report(Error::Save("scan cache", e));
+        }
+    }
+
+    fn resolve_paths(&mut self, env: &HashMap<String, String>) {
+        if !self.resolving_paths {
+            return;
+        }
+        self.resolving_paths = false;
+        let warnings = self.config.expand_paths(env);
+        self.errors.extend(warnings);
+        self.config.make_absolute(&self.cwd);
+        self.load_data();
+        self.start_scan();
+        self.open_headless();
+    }
+
+    // Lists the dirs of the last scan until the new one is done
+    fn load_cache(&mut self) {
+        let dir = store::dir_or_tmp(&self.config, &self.cwd);
+        let cache = ScanCache::new(&dir, self.cache_key.clone());
+        self.dirlist.add_dirs(cache.load(), Source::Cache);
+        self.cache = Some(cache);
+    }
+
+    // Loads what previous loads remembered, the data_dir has to be expanded first
+    fn load_data(&mut self) {
+        if self.cache.is_none() {
+            self.load_cache();
+        }
+        let dir = store::dir(&self.config, &self.cwd).unwrap_or_else(|e| {
+            self.report(e);
+            PathBuf::from(store::TMP_DIR)
+        });
+        self.frecency.load(&dir);
+        self.session_dirs.load(&dir);
+        self.dirlist.set_boosts(self.boosts());
+        self.unifiedlist.set_boosts(self.boosts());
+    }
+
+    // Switching to the current session is not allowed, so it has to be known
+    // before acting on anything, as do the permissions and the expanded paths
+    fn is_ready(&self) -> bool {
+        self.permissions_granted && !self.resolving_paths && !self.current_session.is_empty()
+    }
+
+    // Acts on open_dir or open_session then closes, the picker is only shown for errors
+    fn open_headless(&mut self) {
+        if !self.is_ready() {
+            return;
+        }
+        let result = match (self.config.open_dir.take(), self.config.open_session.take()) {
+            (Some(dir), _) => self.switch_session_with_cwd(&dir),
+            (None, Some(session)) => {
+                if session != self.current_session {
+                    switch_session(Some(&session));
+                }
+                Ok(())
             }
+            (None, None) => return,
+        };
+        match result {
+            Ok(()) => close_self(),
+            Err(e) => self.report(e),
+        }
+    }
+
+    // Shows the picker with an error instead of staying blank forever
+    fn cancel_headless(&mut self) {
+        let setting = match (self.config.open_dir.take(), self.config.open_session.take()) {
+            (Some(_), _) => "open_dir",
+            (None, Some(_)) => "open_session",
+            (None, None) => return,
+        };
+        self.report(Error::PermissionsDenied(setting));
+    }
+
+    // Runs the queued pipe commands that can run now, the others keep waiting
+    fn run_pending_pipes(&mut self) {
+        let pending = std::mem::take(&mut self.pending_pipes);
+        for (command, pipe_id) in pending {
+            // The dirs are only all listed once the scan is done
+            let waits_for_scan = matches!(command, PipeCommand::ListDirs | PipeCommand::Rescan);
+            if !self.is_ready() || (waits_for_scan && self.is_scanning()) {
+                self.pending_pipes.push((command, pipe_id));
+                continue;
+            }
+            let reply = self.handle_pipe_command(command);
+            reply_to_pipe(pipe_id.as_deref(), reply);
+        }
+    }
+
+    // The queued pipe commands would never run
+    fn cancel_pending_pipes(&mut self) {
+        for (_, pipe_id) in std::mem::take(&mut self.pending_pipes) {
+            let error = Error::PermissionsDenied("pipe").to_string();
+            reply_to_pipe(pipe_id.as_deref(), Err(error));
+        }
+    }
+
+    fn start_scan(&mut self) {
+        self.dirlist.mark_stale();
+        let mut sources = std::mem::take(&mut self.sources);
+        let ctx = self.source_context();
+        let found: Vec<_> = sources.iter_mut().map(|s| (s.source(), s.start(&ctx))).collect();
+        self.sources = sources;
+        self.add_found(found);
+        if !self.is_scanning() {
+            self.finish_scan();
+        }
+    }
+
+    // Passes the event to every source, returns whether something was found
+    fn update_sources(&mut self, event: &Event) -> bool {
+        let was_scanning = self.is_scanning();
+        let mut sources = std::mem::take(&mut self.sources);
+        let ctx = self.source_context();
+        let found: Vec<_> = sources
+            .iter_mut()
+            .map(|s| (s.source(), s.update(event, &ctx)))
+            .collect();
+        self.sources = sources;
+        let changed = found
+            .iter()
+            .any(|(_, f)| !f.dirs.is_empty() || !f.errors.is_empty());
+        // Results of directories that answered after the scan gave up
+        let late = !was_scanning
+            && found
+                .iter()
+                .any(|(s, f)| *s == Source::Scan && !f.dirs.is_empty());
+        self.add_found(found);
+        if late || (was_scanning && !self.is_scanning()) {
+            self.finish_scan();
+            return true;
+        }
+        changed
+    }
+
+    fn add_found(&mut self, found: Vec<(Source, Found)>) {
+        let config = &self.config;
+        let mut worktrees = vec![];
+        let found: Vec<(Source, Vec<String>)> = found
+            .into_iter()
+            .map(|(source, found)| {
+                self.errors.extend(found.errors);
+                if !found.boosts.is_empty() {
+                    self.source_boosts.insert(source, found.boosts);
+                }
+                worktrees.extend(found.worktrees);
+                let dirs = found
+                    .dirs
+                    .into_iter()
+                    .filter(|dir| config.is_listed(Path::new(dir)))
+                    .collect();
+                (source, dirs)
+            })
+            .collect();
+        let mut sources = std::mem::take(&mut self.sources);
+        let ctx = self.source_context();
+        for (source, dirs) in found.iter().filter(|(_, dirs)| !dirs.is_empty()) {
+            sources.iter_mut().for_each(|s| s.listed(*source, dirs, &ctx));
         }
-        
-        // Update the directory list with all valid directories
-        if !self.valid_dirs.is_empty() {
-            self.dirlist.update_dirs(self.valid_dirs.clone());
+        self.sources = sources;
+        if found.iter().all(|(_, dirs)| dirs.is_empty()) {
+            return;
+        }
+        self.dirlist.add_worktrees(worktrees);
+        for (source, dirs) in found.into_iter().filter(|(_, dirs)| !dirs.is_empty()) {
+            self.dirlist.add_dirs(dirs, source);
+        }
+        self.dirlist.set_boosts(self.boosts());
+        self.unifiedlist.set_boosts(self.boosts());
+    }
+
+    fn source_context(&self) -> Context<'_> {
+        Context {
+            config: &self.config,
+            cwd: &self.cwd,
+            permissions_granted: self.permissions_granted,
+        }
+    }
+
+    fn is_scanning(&self) -> bool {
+        self.sources.iter().any(|s| s.is_running())
+    }
+
+    // Runs a command received through `zellij pipe`, returning its reply
+    fn handle_pipe_command(&mut self, command: PipeCommand) -> Result<String, String> {
+        match command {
+            PipeCommand::Open(dir) => {
+                // The cwd of `zellij pipe` isn't sent, and `~` is only expanded by the shell
+                if !dir.is_absolute() {
+                    return Err(format!("{}: not an absolute path", dir.display()));
+                }
+                let dir = paths::absolute(&dir, &self.cwd);
+                self.switch_session_with_cwd(&dir).map_err(|e| e.to_string())?;
+                Ok(String::new())
+            }
+            PipeCommand::Attach(session) => {
+                if self.sesslist.get_session(&session).is_none() {
+                    return Err(format!("{session}: no such session"));
+                }
+                if session != self.current_session {
+                    switch_session(Some(&session));
+                }
+                Ok(String::new())
+            }
+            PipeCommand::ListDirs => Ok(self
+                .dirlist
+                .dirs()
+                .iter()
+                .map(|dir| format!("{dir}\n"))
+                .collect()),
+            PipeCommand::Rescan => {
+                if self.is_scanning() || self.resolving_paths {
+                    return Err("a scan is already running".to_string());
+                }
+                // Reported again by the new scan
+                self.errors
+                    .retain(|e| !matches!(e, Error::Unreachable { .. } | Error::Missing { .. }));
+                self.start_scan();
+                Ok(String::new())
+            }
         }
     }
 }
@@ -154,193 +740,343 @@ impl ZellijPlugin for State {
             PermissionType::RunCommands,
             PermissionType::ChangeApplicationState,
             PermissionType::ReadApplicationState,
+            PermissionType::ReadCliPipes,
         ]);
         subscribe(&[
             EventType::Key,
             EventType::FileSystemUpdate,
             EventType::SessionUpdate,
+            EventType::Timer,
+            EventType::RunCommandResult,
+            EventType::PermissionRequestResult,
         ]);
         self.dirlist.reset();
         self.sesslist.reset();
         self.textinput.reset();
-        self.valid_dirs.clear();
-        self.pending_scans.clear();
-        self.root_dirs_set.clear();
-        
-        let host = PathBuf::from(ROOT);
-        // Scan root directories for projects with root files
-        for dir in &self.config.root_dirs {
-            let relative_path = match dir.strip_prefix(self.cwd.as_path()) {
-                Ok(p) => p,
-                Err(_) => continue,
-            };
-            let host_path = host.join(relative_path);
-            self.root_dirs_set.insert(host_path.clone());
-            scan_host_folder(&host_path);
+        self.sources = sources::all();
+        self.dirlist.set_boosts(self.boosts());
+        self.unifiedlist.reset();
+        self.unifiedlist.set_boosts(self.boosts());
+
+        self.errors = std::mem::take(&mut self.config.warnings);
+        self.show_errors = false;
+        // Keyed by the config as written, the cached dirs don't wait for the
+        // host environment unless the data_dir itself needs it
+        self.cache_key = cache::cache_key(&self.config, &self.cwd);
+        self.cache = None;
+        if !self.config.data_dir.as_deref().is_some_and(paths::needs_env) {
+            self.load_cache();
         }
-        
-        // Add direct directories without scanning
-        let direct_dirs: Vec<String> = self.config.dirs
-            .iter()
-            .map(|p| p.to_string_lossy().to_string())
-            .collect();
-        if !direct_dirs.is_empty() {
-            self.valid_dirs.extend(direct_dirs);
-            self.dirlist.update_dirs(self.valid_dirs.clone());
+        // Paths using ~ or variables are expanded once the host environment is known
+        self.resolving_paths = self.config.needs_env();
+        match self.resolving_paths {
+            // Only answered once the permissions are granted, so also asked for then
+            true => request_env(),
+            false => {
+                self.config.make_absolute(&self.cwd);
+                self.load_data();
+                self.start_scan();
+            }
         }
-        self.screen = Screen::SearchDirs;
-        self.textinput_dump = "".to_string();
+        self.screen = self.config.initial_screen;
+        self.textinput_dumps.clear();
+        self.textinput.replace_text(&self.config.initial_query);
+        self.apply_search_term();
+        self.show_preview = true;
+        self.prompt = None;
     }
 
     fn update(&mut self, event: Event) -> bool {
-        let mut should_render = false;
+        let mut should_render = self.update_sources(&event);
         match event {
-            Event::FileSystemUpdate(paths) => {
-                self.process_filesystem_update(&paths);
+            Event::PermissionRequestResult(status) => {
+                self.permissions_granted = status == PermissionStatus::Granted;
+                if self.resolving_paths {
+                    match status {
+                        PermissionStatus::Granted => request_env(),
+                        PermissionStatus::Denied => self.resolve_paths(&HashMap::new()),
+                    }
+                }
+                match status {
+                    PermissionStatus::Granted => self.open_headless(),
+                    PermissionStatus::Denied => {
+                        self.cancel_headless();
+                        self.cancel_pending_pipes();
+                    }
+                }
                 should_render = true;
             }
+            Event::RunCommandResult(exit_code, stdout, stderr, context) => {
+                should_render |= self.handle_command_result(exit_code, &stdout, &stderr, &context);
+            }
             Event::SessionUpdate(sessions, resurrectables) => {
                 //TODO: I may want to handle this inside the sess list
                 //and also set the cursor always to the current session
                 let alive_sessions = sessions.into_iter().map(|s| {
-                    if s.is_current_session {
-                        self.current_session = s.name.clone();
-                        Session {
-                            name: s.name,
-                            icon: " ".to_string(),
-                        }
-                    } else {
-                        Session {
-                            name: s.name,
-                            icon: " ".to_string(),
+                    let icon = match s.is_current_session {
+                        true => {
+                            self.current_session = s.name.clone();
+                            " "
                         }
+                        false => " ",
+                    };
+                    Session {
+                        name: s.name.clone(),
+                        icon: icon.to_string(),
+                        dir: self.session_dir(&s.name),
+                        info: Some(s),
                     }
-                });
+                }).collect::<Vec<_>>();
                 let resurrectable_sessions = resurrectables.into_iter().map(|(name, _)| Session {
+                    dir: self.session_dir(&name),
                     name,
                     icon: "󰤄".to_string(),
+                    info: None,
                 });
                 self.sesslist
-                    .update_sessions(alive_sessions.chain(resurrectable_sessions).collect());
+                    .update_sessions(alive_sessions.into_iter().chain(resurrectable_sessions).collect());
+                self.open_headless();
+                should_render = true;
+            }
+            Event::Key(key) if self.prompt.is_some() => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.handle_prompt_key(prompt, &key);
+                }
                 should_render = true;
             }
             Event::Key(key) => {
                 should_render = true;
-                match key {
-                    KeyWithModifier {
-                        bare_key: BareKey::Tab,
-                        key_modifiers: _,
-                    } => {
-                        self.textinput_dump =
-                            self.textinput.replace_text(self.textinput_dump.as_str());
-                        self.screen = match self.screen {
-                            Screen::SearchDirs => Screen::SearchSessions,
-                            Screen::SearchSessions => Screen::SearchDirs,
-                        }
+                match self.config.keymap.get(&key) {
+                    Some(Action::SwitchScreen) => {
+                        let next = self.screen.next();
+                        let text = self.textinput_dumps.remove(&next).unwrap_or_default();
+                        let prev_text = self.textinput.replace_text(text.as_str());
+                        self.textinput_dumps.insert(self.screen, prev_text);
+                        self.screen = next;
                     }
-                    KeyWithModifier {
-                        bare_key: BareKey::Esc,
-                        key_modifiers: _,
-                    } => {
+                    Some(Action::Close) => {
                         close_self();
                     }
-                    KeyWithModifier {
-                        bare_key: BareKey::Char('n'),
-                        key_modifiers: km,
-                    } if km.contains(&KeyModifier::Ctrl) => match self.screen {
+                    Some(Action::Next) => match self.screen {
                         Screen::SearchDirs => self.dirlist.handle_down(),
                         Screen::SearchSessions => self.sesslist.handle_down(),
+                        Screen::Unified => self.unifiedlist.handle_down(),
                     },
-                    KeyWithModifier {
-                        bare_key: BareKey::Char('p'),
-                        key_modifiers: km,
-                    } if km.contains(&KeyModifier::Ctrl) => match self.screen {
+                    Some(Action::Previous) => match self.screen {
                         Screen::SearchDirs => self.dirlist.handle_up(),
                         Screen::SearchSessions => self.sesslist.handle_up(),
+                        Screen::Unified => self.unifiedlist.handle_up(),
                     },
-                    KeyWithModifier {
-                        bare_key: BareKey::Char('x'),
-                        key_modifiers: km,
-                    } if km.contains(&KeyModifier::Ctrl) => match self.screen {
-                        Screen::SearchDirs => {}
-                        Screen::SearchSessions => {
-                            self.sesslist.kill_selected();
+                    Some(Action::TogglePreview) => {
+                        self.show_preview = !self.show_preview;
+                    }
+                    Some(Action::ToggleErrors) => {
+                        self.show_errors = !self.show_errors && !self.errors.is_empty();
+                    }
+                    Some(Action::Kill) => self.kill_selected(),
+                    Some(Action::OpenTab) => {
+                        if let Some(dir) = self.selected_dir() {
+                            if self.open_dir_as_tab(Path::new(&dir)) {
+                                close_self();
+                            }
+                        }
+                    }
+                    Some(Action::OpenPane) => {
+                        if let Some(dir) = self.selected_dir() {
+                            self.open_dir_as_pane(Path::new(&dir));
+                            close_self();
                         }
+                    }
+                    Some(Action::ToggleMark) => match self.screen {
+                        Screen::SearchDirs => self.dirlist.toggle_mark_selected(),
+                        Screen::SearchSessions => self.sesslist.toggle_mark_selected(),
+                        Screen::Unified => self.notice_no_marks(),
+                    },
+                    Some(Action::MarkAll) => match self.screen {
+                        Screen::SearchDirs => self.dirlist.mark_all_filtered(),
+                        Screen::SearchSessions => self.sesslist.mark_all_filtered(),
+                        Screen::Unified => self.notice_no_marks(),
+                    },
+                    Some(Action::ClearMarks) => match self.screen {
+                        Screen::SearchDirs => self.dirlist.clear_marks(),
+                        Screen::SearchSessions => self.sesslist.clear_marks(),
+                        Screen::Unified => self.notice_no_marks(),
                     },
-                    KeyWithModifier {
-                        bare_key: BareKey::Enter,
-                        key_modifiers: _,
-                    } => match self.screen {
+                    Some(Action::Rename) => {
+                        if self.current_session_selected("Only the current session can be renamed") {
+                            let search_term = self.textinput.replace_text(&self.current_session);
+                            self.prompt = Some(Prompt::Rename { search_term });
+                        }
+                    }
+                    Some(Action::DisconnectOthers) => {
+                        let notice = "Only the clients of the current session can be disconnected";
+                        if self.current_session_selected(notice) {
+                            self.run_or_confirm(SessionAction::DisconnectOthers(
+                                self.current_session.clone(),
+                            ));
+                        }
+                    }
+                    Some(Action::Accept) => match self.screen {
                         Screen::SearchDirs => {
-                            if let Some(selected) = self.dirlist.get_selected() {
-                                let _ = self.switch_session_with_cwd(Path::new(&selected));
-                                close_self();
+                            // Create a session for every marked dir, the last one is attached
+                            let marked = self.dirlist.marked();
+                            let dirs = match marked.is_empty() {
+                                true => self.dirlist.get_selected().into_iter().collect(),
+                                false => marked,
+                            };
+                            if !dirs.is_empty() {
+                                let results: Vec<_> = dirs
+                                    .iter()
+                                    .map(|dir| self.switch_session_with_cwd(Path::new(dir)))
+                                    .collect();
+                                // Stay open when nothing could be switched to, to show why
+                                let switched = results.iter().any(Result::is_ok);
+                                results.into_iter().filter_map(Result::err).for_each(|e| self.report(e));
+                                if switched {
+                                    close_self();
+                                }
                             }
                         }
                         Screen::SearchSessions => {
                             if let Some(selected) = self.sesslist.get_selected() {
-                                let _ = switch_session(Some(&selected));
+                                switch_session(Some(&selected));
                                 close_self();
                             }
                         }
-                    },
-                    KeyWithModifier {
-                        bare_key: BareKey::Backspace,
-                        key_modifiers: _,
-                    } => {
-                        self.textinput.handle_backspace();
-                        match self.screen {
-                            Screen::SearchDirs => self
-                                .dirlist
-                                .set_search_term(self.textinput.get_text().as_str()),
-                            Screen::SearchSessions => self
-                                .sesslist
-                                .set_search_term(self.textinput.get_text().as_str()),
+                        Screen::Unified => {
+                            if let Some(selected) = self.unifiedlist.get_selected() {
+                                let result = match (selected.target, selected.session) {
+                                    // Attach to the existing session, or resurrect it
+                                    (Target::Dir(dir), Some(session)) => {
+                                        self.frecency.record(Path::new(&dir));
+                                        self.save_frecency();
+                                        if session != self.current_session {
+                                            switch_session(Some(&session));
+                                        }
+                                        Ok(())
+                                    }
+                                    (Target::Dir(dir), None) => {
+                                        self.switch_session_with_cwd(Path::new(&dir))
+                                    }
+                                    (Target::Session(session), _) => {
+                                        if session != self.current_session {
+                                            switch_session(Some(&session));
+                                        }
+                                        Ok(())
+                                    }
+                                };
+                                match result {
+                                    Ok(()) => close_self(),
+                                    Err(e) => self.report(e),
+                                }
+                            }
                         }
-                    }
-                    KeyWithModifier {
-                        bare_key: BareKey::Char(c),
-                        key_modifiers: _,
-                    } => {
-                        self.textinput.handle_char(c);
-                        match self.screen {
-                            Screen::SearchDirs => self
-                                .dirlist
-                                .set_search_term(self.textinput.get_text().as_str()),
-                            Screen::SearchSessions => self
-                                .sesslist
-                                .set_search_term(self.textinput.get_text().as_str()),
+                    },
+                    None => {
+                        if self.textinput.handle_key(&key) {
+                            self.apply_search_term();
                         }
                     }
-                    _ => (),
                 }
             }
             _ => (),
         };
+        // Only kept up to date while shown, it is rebuilt from the other two lists
+        if should_render && matches!(self.screen, Screen::Unified) {
+            self.unifiedlist
+                .update(self.dirlist.dirs(), self.sesslist.sessions());
+        }
+        if should_render {
+            self.request_preview();
+        }
+        self.run_pending_pipes();
         should_render
     }
 
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        if pipe_message.name != pipe::PIPE_NAME {
+            return false;
+        }
+        let pipe_id = match &pipe_message.source {
+            PipeSource::Cli(pipe_id) => Some(pipe_id.clone()),
+            _ => None,
+        };
+        let Some(command) = pipe_message.payload.as_deref().and_then(PipeCommand::parse) else {
+            reply_to_pipe(pipe_id.as_deref(), Err(pipe::USAGE.to_string()));
+            return false;
+        };
+        // A plugin started by `zellij pipe --plugin` gets the message right after
+        // loading, the CLI waits for the reply until the command can run
+        if let Some(pipe_id) = &pipe_id {
+            block_cli_pipe_input(pipe_id);
+        }
+        self.pending_pipes.push((command, pipe_id));
+        self.run_pending_pipes();
+        true
+    }
+
     fn render(&mut self, rows: usize, cols: usize) {
+        // Nothing to show until open_dir or open_session is acted on
+        if self.config.open_dir.is_some() || self.config.open_session.is_some() {
+            return;
+        }
+        let status_rows = match self.show_errors {
+            true => self.errors.len(),
+            false => self.errors.len().min(1),
+        };
+        let list_rows = rows.saturating_sub(4 + status_rows);
+        let show_preview = self.show_preview && cols >= PREVIEW_MIN_COLS;
+        let list_cols = match show_preview {
+            true => cols * 3 / 5,
+            false => cols,
+        };
         println!();
         match self.screen {
-            Screen::SearchDirs => self.dirlist.render(rows.saturating_sub(4), cols),
-            Screen::SearchSessions => self.sesslist.render(rows.saturating_sub(4), cols),
+            Screen::SearchDirs => self.dirlist.render(list_rows, list_cols),
+            Screen::SearchSessions => self.sesslist.render(list_rows, list_cols),
+            Screen::Unified => self.unifiedlist.render(list_rows, list_cols),
+        }
+        if show_preview {
+            let x = list_cols + 2;
+            self.render_preview(x, 1, cols.saturating_sub(x), list_rows);
         }
         println!();
-        self.textinput.render(rows, cols);
+        if let Some(prompt) = &self.prompt {
+            prompt.render();
+        }
+        if matches!(self.prompt, None | Some(Prompt::Rename { .. })) {
+            self.textinput.render(rows, cols);
+        }
         println!();
-        if !self.debug.is_empty() {
-            println!();
-            println!("{}", self.debug);
+        self.render_status(cols);
+    }
+}
+
+// Layouts that are empty, e.g. couldn't be dumped, open a single pane tab
+fn open_layout_as_tab(layout: &str, dir: &Path) {
+    match layout.trim().is_empty() {
+        true => {
+            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+            new_tabs_with_layout(&layout::tab_layout(&name, dir));
         }
+        false => new_tabs_with_layout(&layout::with_cwd(layout, dir)),
     }
 }
 
-fn is_hidden(path: &Path) -> bool {
-    const WHITELIST: [&str; 1] = [".config"];
+// Messages sent from a keybinding or another plugin get no reply
+fn reply_to_pipe(pipe_id: Option<&str>, reply: Result<String, String>) {
+    let Some(pipe_id) = pipe_id else {
+        return;
+    };
+    match reply {
+        Ok(output) => cli_pipe_output(pipe_id, &output),
+        Err(error) => cli_pipe_output(pipe_id, &format!("error: {error}\n")),
+    }
+    unblock_cli_pipe_input(pipe_id);
+}
 
-    path.file_name()
-        .and_then(|s| s.to_str())
-        .map(|s| s.starts_with('.') && !WHITELIST.contains(&s))
-        .unwrap_or(false)
+fn request_env() {
+    let context = BTreeMap::from([(COMMAND_KEY.to_string(), paths::ENV.to_string())]);
+    run_command(&["env"], context);
 }
+
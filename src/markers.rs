@@ -0,0 +1,110 @@
+use crate::glob::glob_match;
+
+/// How the positive markers of a directory have to be found
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MarkerMode {
+    #[default]
+    Any,
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Marker {
+    pattern: String,
+    negated: bool,
+}
+
+/// The rules deciding which directories are projects. Positive markers are
+/// globs matched against the names of a directory's children, negated ones
+/// (`!pattern`) reject a directory when they match the directory's own path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootMarkers {
+    markers: Vec<Marker>,
+    pub mode: MarkerMode,
+}
+
+impl Default for RootMarkers {
+    fn default() -> Self {
+        Self::from(".git")
+    }
+}
+
+impl From<&str> for RootMarkers {
+    // "Cargo.toml;*.sln;!node_modules"
+    fn from(markers: &str) -> Self {
+        let markers = markers
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.strip_prefix('!') {
+                Some(pattern) => Marker { pattern: pattern.to_string(), negated: true },
+                None => Marker { pattern: s.to_string(), negated: false },
+            })
+            .collect();
+        Self { markers, mode: MarkerMode::default() }
+    }
+}
+
+impl std::fmt::Display for RootMarkers {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let markers: Vec<String> = self
+            .markers
+            .iter()
+            .map(|m| match m.negated {
+                true => format!("!{}", m.pattern),
+                false => m.pattern.clone(),
+            })
+            .collect();
+        let mode = match self.mode {
+            MarkerMode::Any => "any",
+            MarkerMode::All => "all",
+        };
+        write!(f, "{}:{}", mode, markers.join(";"))
+    }
+}
+
+impl RootMarkers {
+    /// Whether the directory at `path` with the given child names is a project
+    pub fn matches(&self, path: &str, children: &[&str]) -> bool {
+        if self.excludes(path) {
+            return false;
+        }
+        let mut positive = self.markers.iter().filter(|m| !m.negated).peekable();
+        if positive.peek().is_none() {
+            return false;
+        }
+        let found = |m: &Marker| children.iter().any(|c| glob_match(&m.pattern, c));
+        match self.mode {
+            MarkerMode::Any => positive.any(found),
+            MarkerMode::All => positive.all(found),
+        }
+    }
+
+    /// Whether a negated marker matches, such directories are not descended into
+    pub fn excludes(&self, path: &str) -> bool {
+        self.markers
+            .iter()
+            .any(|m| m.negated && glob_match(&m.pattern, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_markers() {
+        let markers = RootMarkers::from("Cargo.toml;*.sln;!**/target/**;!node_modules");
+        assert!(markers.matches("/home/me/api", &["Cargo.toml", "src"]));
+        assert!(markers.matches("/home/me/app", &["App.sln"]));
+        assert!(!markers.matches("/home/me/api/target/pkg", &["Cargo.toml"]));
+        assert!(markers.matches("/home/me/web", &["Cargo.toml", "node_modules"]));
+        assert!(!markers.matches("/home/me/web/node_modules", &["Cargo.toml"]));
+        assert!(!markers.matches("/home/me/docs", &["README.md"]));
+        assert!(markers.excludes("/home/me/web/node_modules"));
+
+        let mut markers = RootMarkers::from("Cargo.toml;package.json");
+        markers.mode = MarkerMode::All;
+        assert!(!markers.matches("/home/me/api", &["Cargo.toml"]));
+        assert!(markers.matches("/home/me/app", &["Cargo.toml", "package.json"]));
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+
+/// The entries of a list marked for a bulk action
+#[derive(Debug, Default)]
+pub struct Marks {
+    marked: HashSet<String>,
+}
+
+impl Marks {
+    pub fn toggle(&mut self, item: String) {
+        if !self.marked.remove(&item) {
+            self.marked.insert(item);
+        }
+    }
+
+    pub fn mark_all(&mut self, items: impl IntoIterator<Item = String>) {
+        self.marked.extend(items);
+    }
+
+    pub fn clear(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn contains(&self, item: &str) -> bool {
+        self.marked.contains(item)
+    }
+
+    // The marked ones among `items`, in their order
+    pub fn in_order(&self, items: &[String]) -> Vec<String> {
+        items
+            .iter()
+            .filter(|item| self.marked.contains(*item))
+            .cloned()
+            .collect()
+    }
+
+    // Rendered before each row while some entries are marked. Marks are also
+    // shown on the highlighted row, which only has one color.
+    pub fn prefix(&self, item: &str) -> &'static str {
+        match (self.marked.is_empty(), self.contains(item)) {
+            (true, _) => "",
+            (false, true) => "* ",
+            (false, false) => "  ",
+        }
+    }
+}
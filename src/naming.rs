@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+// Characters that zellij or the shell handle poorly in session names
+const INVALID_CHARS: [char; 4] = ['/', '.', ':', '\\'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionNaming {
+    pub template: String,    // ex: "{parent}/{name}", "{name}@{git_branch}"
+    pub replacement: String, // Replaces whitespace and invalid chars
+}
+
+impl Default for SessionNaming {
+    fn default() -> Self {
+        Self {
+            template: "{name}".to_string(),
+            replacement: "_".to_string(),
+        }
+    }
+}
+
+impl SessionNaming {
+    /// Renders the template for `dir`, `branch` is substituted for `{git_branch}`.
+    pub fn render(&self, dir: &Path, branch: Option<&str>) -> String {
+        let name = component(dir.file_name());
+        let parent = component(dir.parent().and_then(|p| p.file_name()));
+        let name = self
+            .template
+            .replace("{name}", &name)
+            .replace("{parent}", &parent)
+            .replace("{git_branch}", branch.unwrap_or(""));
+        self.sanitize(&name)
+    }
+
+    pub fn sanitize(&self, name: &str) -> String {
+        let invalid = |c: char| c.is_whitespace() || c.is_control() || INVALID_CHARS.contains(&c);
+        let mut sanitized = String::new();
+        // Drop leading and trailing invalid chars rather than replacing them
+        for c in name.trim_matches(invalid).chars() {
+            if invalid(c) {
+                // Collapse runs of invalid chars into a single replacement
+                if !sanitized.ends_with(&self.replacement) || self.replacement.is_empty() {
+                    sanitized.push_str(&self.replacement);
+                }
+            } else {
+                sanitized.push(c);
+            }
+        }
+        sanitized
+    }
+
+    /// Prefixes `name` with as many parent dirs of `dir` as needed for it to differ
+    /// from the names of `others` that render to the same name, and from the
+    /// `taken` names of sessions whose dir is unknown.
+    pub fn disambiguate(
+        &self,
+        name: String,
+        dir: &Path,
+        others: &[(PathBuf, String)],
+        taken: &[String],
+    ) -> String {
+        let conflicts: Vec<&PathBuf> = others
+            .iter()
+            .filter(|(other, other_name)| other != dir && *other_name == name)
+            .map(|(other, _)| other)
+            .collect();
+        if conflicts.is_empty() && !taken.contains(&name) {
+            return name;
+        }
+        let depth = dir.components().count();
+        for n in 1..depth {
+            let candidate = self.with_parents(&name, dir, n);
+            if !taken.contains(&candidate)
+                && conflicts
+                    .iter()
+                    .all(|other| self.with_parents(&name, other, n) != candidate)
+            {
+                return candidate;
+            }
+        }
+        self.sanitize(&dir.to_string_lossy())
+    }
+
+    fn with_parents(&self, name: &str, dir: &Path, n: usize) -> String {
+        let parents: Vec<String> = dir
+            .ancestors()
+            .skip(1)
+            .take(n)
+            .map(|p| component(p.file_name()))
+            .collect();
+        let prefix: Vec<&str> = parents.iter().rev().map(String::as_str).collect();
+        self.sanitize(&format!("{}/{}", prefix.join("/"), name))
+    }
+}
+
+fn component(name: Option<&std::ffi::OsStr>) -> String {
+    name.map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Reads the checked out branch of the repo at `dir` without running git,
+/// `cwd` is the plugin cwd, mounted at `ROOT`.
+pub fn git_branch(dir: &Path, cwd: &Path) -> Option<String> {
+    let to_host = |path: &Path| paths::to_host(path, cwd);
+    let dot_git = to_host(&dir.join(".git"))?;
+    let git_dir = if dot_git.is_file() {
+        // Worktrees and submodules point to their git dir: "gitdir: /path/to/dir"
+        let content = fs::read_to_string(&dot_git).ok()?;
+        let git_dir = dir.join(content.trim().strip_prefix("gitdir: ")?);
+        to_host(&git_dir)?
+    } else {
+        dot_git
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: refs/heads/") {
+        Some(branch) => Some(branch.to_string()),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_sanitize() {
+        let naming = SessionNaming {
+            template: "{parent}/{name}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(naming.render(Path::new("/home/me/work/my app.v2"), None), "work_my_app_v2");
+        assert_eq!(naming.sanitize(" __init__. "), "__init__");
+    }
+
+    #[test]
+    fn test_disambiguate() {
+        let naming = SessionNaming::default();
+        let others = vec![
+            (PathBuf::from("/home/me/work/api"), "api".to_string()),
+            (PathBuf::from("/home/me/oss/api"), "api".to_string()),
+            (PathBuf::from("/home/me/oss/web"), "web".to_string()),
+        ];
+        let dir = Path::new("/home/me/work/api");
+        assert_eq!(naming.disambiguate("api".to_string(), dir, &others, &[]), "work_api");
+        let dir = Path::new("/home/me/oss/web");
+        assert_eq!(naming.disambiguate("web".to_string(), dir, &others, &[]), "web");
+    }
+
+    #[test]
+    fn test_disambiguate_from_sessions_of_unknown_dirs() {
+        let naming = SessionNaming::default();
+        // Sessions created elsewhere, or before their dir was remembered
+        let taken = vec!["api".to_string(), "oss_api".to_string()];
+        let dir = Path::new("/home/me/oss/api");
+        assert_eq!(naming.disambiguate("api".to_string(), dir, &[], &taken), "me_oss_api");
+        let dir = Path::new("/home/me/oss/web");
+        assert_eq!(naming.disambiguate("web".to_string(), dir, &[], &taken), "web");
+    }
+}
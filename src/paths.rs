@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::Error;
+use crate::ROOT;
+
+pub const ENV: &str = "env";
+
+/// Whether the path needs the host environment to be resolved
+pub fn needs_env(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with('~') || path.contains('$')
+}
+
+// The output of `env`, one KEY=value per line
+pub fn parse_env(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Expands a leading `~` and `$VAR` or `${VAR}` using the host environment
+pub fn expand(path: &Path, env: &HashMap<String, String>) -> Result<PathBuf, Error> {
+    let path = path.to_string_lossy();
+    let var = |name: &str| {
+        env.get(name)
+            .cloned()
+            .ok_or_else(|| Error::UnsetVariable {
+                path: path.to_string(),
+                var: name.to_string(),
+            })
+    };
+    let mut expanded = String::new();
+    let mut rest = path.as_ref();
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&var("HOME")?);
+        rest = &rest[1..];
+    }
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let (name, len) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => return Err(Error::UnclosedVariable(path.to_string())),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], end)
+            }
+        };
+        expanded.push_str(&var(name)?);
+        rest = &rest[len..];
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+// Resolves `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The path on the host, relative paths are relative to the plugin's cwd
+pub fn absolute(path: &Path, cwd: &Path) -> PathBuf {
+    normalize(&cwd.join(path))
+}
+
+/// Where a path is mounted inside the plugin, only the descendants of the
+/// plugin's cwd are visible. Relative paths are relative to the cwd.
+pub fn to_host(path: &Path, cwd: &Path) -> Option<PathBuf> {
+    let cwd = normalize(cwd);
+    absolute(path, &cwd)
+        .strip_prefix(&cwd)
+        .ok()
+        .map(|p| PathBuf::from(ROOT).join(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_and_translate() {
+        let env = parse_env("HOME=/home/me\nWORK=/srv/work\n");
+        let expand = |path| expand(Path::new(path), &env).ok();
+        assert_eq!(expand("~/src"), Some(PathBuf::from("/home/me/src")));
+        assert_eq!(expand("${WORK}/api"), Some(PathBuf::from("/srv/work/api")));
+        assert_eq!(expand("$WORK"), Some(PathBuf::from("/srv/work")));
+        assert_eq!(expand("${WORK"), None);
+        assert_eq!(expand("$NOPE/api"), None);
+
+        let cwd = Path::new("/home/me/");
+        assert_eq!(to_host(Path::new("/home/me/src/"), cwd), Some(PathBuf::from("/host/src")));
+        assert_eq!(to_host(Path::new("/home/me/../me/src"), cwd), Some(PathBuf::from("/host/src")));
+        assert_eq!(to_host(Path::new("src"), cwd), Some(PathBuf::from("/host/src")));
+        assert_eq!(to_host(Path::new("/home/me"), cwd), Some(PathBuf::from("/host")));
+        assert_eq!(to_host(Path::new(""), cwd), Some(PathBuf::from("/host")));
+        assert_eq!(to_host(Path::new("/srv/work"), cwd), None);
+        assert_eq!(absolute(Path::new(""), cwd), PathBuf::from("/home/me"));
+    }
+}
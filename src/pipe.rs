@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+/// Messages with this name are handled, ex: `zellij pipe -n sessionizer -- list-dirs`
+pub const PIPE_NAME: &str = "sessionizer";
+
+pub const USAGE: &str = "usage: open <dir> | attach <session> | list-dirs | rescan";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipeCommand {
+    Open(PathBuf),
+    Attach(String),
+    ListDirs,
+    Rescan,
+}
+
+impl PipeCommand {
+    pub fn parse(payload: &str) -> Option<Self> {
+        let payload = payload.trim();
+        let (command, arg) = match payload.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (payload, ""),
+        };
+        match (command, arg) {
+            ("open", dir) if !dir.is_empty() => Some(PipeCommand::Open(PathBuf::from(dir))),
+            ("attach", session) if !session.is_empty() => {
+                Some(PipeCommand::Attach(session.to_string()))
+            }
+            ("list-dirs", "") => Some(PipeCommand::ListDirs),
+            ("rescan", "") => Some(PipeCommand::Rescan),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pipe_command() {
+        assert_eq!(
+            PipeCommand::parse("open /home/me/my project\n"),
+            Some(PipeCommand::Open(PathBuf::from("/home/me/my project")))
+        );
+        assert_eq!(
+            PipeCommand::parse("attach api"),
+            Some(PipeCommand::Attach("api".to_string()))
+        );
+        assert_eq!(PipeCommand::parse("list-dirs"), Some(PipeCommand::ListDirs));
+        assert_eq!(PipeCommand::parse(" rescan "), Some(PipeCommand::Rescan));
+        assert_eq!(PipeCommand::parse("open"), None);
+        assert_eq!(PipeCommand::parse("list-dirs now"), None);
+    }
+}
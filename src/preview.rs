@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use zellij_tile::prelude::*;
+
+use crate::sesslist::Session;
+use crate::COMMAND_KEY;
+
+pub const GIT_STATUS: &str = "git_status";
+pub const DIR_KEY: &str = "dir";
+
+const README_FILES: [&str; 3] = ["README.md", "README", "readme.md"];
+const README_LINES: usize = 20;
+
+#[derive(Debug, Default, Clone)]
+struct DirPreview {
+    branch: Option<String>,
+    changes: Option<usize>, // Number of modified or untracked files
+    readme: Vec<String>,
+    // The git status is asked for once the plugin may run commands
+    status_requested: bool,
+}
+
+/// Details shown next to the list for the highlighted directory or session.
+#[derive(Debug, Default)]
+pub struct Preview {
+    dirs: HashMap<String, DirPreview>,
+}
+
+impl Preview {
+    /// Gathers the details of `dir` unless already known, `host_dir` is where
+    /// it is mounted in the plugin's filesystem. The README of a dir that isn't
+    /// mounted can't be read, its git status is still shown. Without the
+    /// permission to run commands, the git status is asked for on a later call.
+    pub fn request_dir(&mut self, dir: &str, host_dir: Option<&Path>, can_run_commands: bool) {
+        let preview = self.dirs.entry(dir.to_string()).or_insert_with(|| DirPreview {
+            readme: read_readme(host_dir),
+            ..Default::default()
+        });
+        if preview.status_requested || !can_run_commands {
+            return;
+        }
+        preview.status_requested = true;
+        let context = BTreeMap::from([
+            (COMMAND_KEY.to_string(), GIT_STATUS.to_string()),
+            (DIR_KEY.to_string(), dir.to_string()),
+        ]);
+        run_command(&["git", "-C", dir, "status", "--porcelain", "--branch"], context);
+    }
+
+    pub fn handle_git_status(&mut self, dir: &str, stdout: &str) {
+        let Some(preview) = self.dirs.get_mut(dir) else {
+            return;
+        };
+        let mut lines = stdout.lines();
+        // "## main...origin/main [ahead 1]" or "## No commits yet on main"
+        preview.branch = lines
+            .next()
+            .and_then(|l| l.strip_prefix("## "))
+            .map(|l| l.strip_prefix("No commits yet on ").unwrap_or(l))
+            .and_then(|l| l.split("...").next())
+            .and_then(|l| l.split_whitespace().next())
+            .map(|b| b.to_string());
+        preview.changes = Some(lines.count());
+    }
+
+    pub fn render_dir(&self, dir: &str, layout: &str, x: usize, y: usize, width: usize, height: usize) {
+        let mut lines = vec![Text::new(dir).color_range(2, ..)];
+        lines.push(Text::new(format!("layout: {layout}")).color_range(1, ..7));
+        if let Some(preview) = self.dirs.get(dir) {
+            if let Some(branch) = &preview.branch {
+                let status = match preview.changes {
+                    Some(0) => "clean".to_string(),
+                    Some(n) => format!("{n} changed"),
+                    None => "".to_string(),
+                };
+                lines.push(Text::new(format!(" {branch} {status}")).color_range(1, ..));
+            }
+            lines.push(Text::new(""));
+            lines.extend(preview.readme.iter().map(Text::new));
+        }
+        render_lines(lines, x, y, width, height);
+    }
+
+    pub fn render_session(&self, session: &Session, x: usize, y: usize, width: usize, height: usize) {
+        let mut lines = vec![Text::new(&session.name).color_range(2, ..)];
+        if let Some(dir) = &session.dir {
+            lines.push(Text::new(dir).color_range(1, ..));
+        }
+        lines.push(Text::new(""));
+        match &session.info {
+            Some(info) => {
+                for tab in &info.tabs {
+                    lines.push(Text::new(format!("{}: {}", tab.position + 1, tab.name)).color_range(0, ..));
+                    let panes = info.panes.panes.get(&tab.position);
+                    panes
+                        .into_iter()
+                        .flatten()
+                        .filter(|p| !p.is_plugin && !p.is_suppressed)
+                        .for_each(|p| {
+                            let command = p.terminal_command.as_ref().unwrap_or(&p.title);
+                            lines.push(Text::new(format!("  {command}")));
+                        });
+                }
+            }
+            None => lines.push(Text::new("resurrectable session")),
+        }
+        render_lines(lines, x, y, width, height);
+    }
+}
+
+fn read_readme(host_dir: Option<&Path>) -> Vec<String> {
+    README_FILES
+        .iter()
+        .find_map(|f| fs::read_to_string(host_dir?.join(f)).ok())
+        .map(|content| {
+            content
+                .lines()
+                .take(README_LINES)
+                .map(|l| l.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn render_lines(lines: Vec<Text>, x: usize, y: usize, width: usize, height: usize) {
+    lines.into_iter().take(height).enumerate().for_each(|(i, line)| {
+        print_text_with_coordinates(line, x, y + i, Some(width), Some(1));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_git_status() {
+        let mut preview = Preview::default();
+        preview.dirs.insert("/home/me/api".to_string(), DirPreview::default());
+        let stdout = "## main...origin/main [ahead 1]\n M src/main.rs\n?? notes.txt\n";
+        preview.handle_git_status("/home/me/api", stdout);
+        let api = &preview.dirs["/home/me/api"];
+        assert_eq!(api.branch.as_deref(), Some("main"));
+        assert_eq!(api.changes, Some(2));
+
+        preview.handle_git_status("/home/me/api", "## No commits yet on dev\n");
+        let api = &preview.dirs["/home/me/api"];
+        assert_eq!(api.branch.as_deref(), Some("dev"));
+        assert_eq!(api.changes, Some(0));
+    }
+}
@@ -0,0 +1,92 @@
+use zellij_tile::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionAction {
+    // Live sessions are killed and resurrectable ones deleted
+    Kill {
+        live: Vec<String>,
+        resurrectable: Vec<String>,
+    },
+    DisconnectOthers(String),
+}
+
+impl SessionAction {
+    pub fn run(&self) {
+        match self {
+            SessionAction::Kill {
+                live,
+                resurrectable,
+            } => {
+                if !live.is_empty() {
+                    kill_sessions(live);
+                }
+                resurrectable.iter().for_each(|s| delete_dead_session(s));
+            }
+            SessionAction::DisconnectOthers(_) => disconnect_other_clients(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            SessionAction::Kill {
+                live,
+                resurrectable,
+            } => match (live.as_slice(), resurrectable.as_slice()) {
+                ([session], []) => format!("Kill session {session}?"),
+                ([], [session]) => format!("Delete resurrectable session {session}?"),
+                _ => format!(
+                    "Kill {} sessions and delete {} resurrectable sessions?",
+                    live.len(),
+                    resurrectable.len()
+                ),
+            },
+            SessionAction::DisconnectOthers(session) => {
+                format!("Disconnect the other clients of {session}?")
+            }
+        }
+    }
+}
+
+/// Takes over the search bar until answered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prompt {
+    Confirm(SessionAction),
+    // The search bar holds the new name, the search term is restored afterwards
+    Rename { search_term: String },
+    Notice(String),
+}
+
+impl Prompt {
+    // A rename prompt only renders its label, the search bar is rendered after it
+    pub fn render(&self) {
+        let (text, hint) = match self {
+            Prompt::Confirm(action) => (action.describe(), " (y/n)"),
+            Prompt::Notice(notice) => (notice.to_string(), ""),
+            Prompt::Rename { .. } => ("Rename session".to_string(), " "),
+        };
+        let text_len = text.chars().count();
+        let prompt = Text::new(format!("{text}{hint}"))
+            .color_range(2, 0..text_len)
+            .color_range(1, text_len..);
+        print_text(prompt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_kill() {
+        let kill = |live: &[&str], resurrectable: &[&str]| SessionAction::Kill {
+            live: live.iter().map(|s| s.to_string()).collect(),
+            resurrectable: resurrectable.iter().map(|s| s.to_string()).collect(),
+        };
+        assert_eq!(kill(&["api"], &[]).describe(), "Kill session api?");
+        assert_eq!(kill(&[], &["old"]).describe(), "Delete resurrectable session old?");
+        assert_eq!(
+            kill(&["api", "web"], &["old"]).describe(),
+            "Kill 2 sessions and delete 1 resurrectable sessions?"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use zellij_tile::prelude::*;
+
+use crate::error::Error;
+use crate::sources::{Context, DirSource, Found, Source};
+use crate::{paths, ROOT};
+
+// A scan still waiting on unanswered directories gives up once no results arrived for this long
+const SCAN_SETTLE_SECS: f64 = 10.0;
+
+/// Finds the projects below the root dirs, a project is a dir containing
+/// the root files. Each dir is scanned separately and the scan stops
+/// descending once a project is found or the max depth is reached.
+#[derive(Debug, Default)]
+pub struct ScanSource {
+    // Track which directories we're waiting to scan, with their (depth, max depth)
+    pending_scans: HashMap<PathBuf, (usize, usize)>,
+    // Track root directories and how deep to scan below them
+    root_dirs: HashMap<PathBuf, usize>,
+    // Track root directories whose scan results haven't arrived yet
+    pending_roots: HashSet<PathBuf>,
+    // Scans requested without results yet, each request is answered by one
+    // update, even for an empty directory
+    unanswered: usize,
+    scanning: bool,
+    // Other timers of the plugin fire too, so the scan only gives up once
+    // this long has passed without results
+    last_result: Option<Instant>,
+}
+
+impl DirSource for ScanSource {
+    fn source(&self) -> Source {
+        Source::Scan
+    }
+
+    fn start(&mut self, ctx: &Context) -> Found {
+        self.pending_scans.clear();
+        self.root_dirs.clear();
+        self.pending_roots.clear();
+        let mut errors = vec![];
+        for root_dir in ctx.config.scanned_root_dirs() {
+            let Some(host_path) = paths::to_host(&root_dir.path, ctx.cwd) else {
+                errors.push(Error::Unreachable {
+                    path: root_dir.path.clone(),
+                    cwd: ctx.cwd.to_path_buf(),
+                });
+                continue;
+            };
+            // Missing root dirs would never be answered
+            if !host_path.is_dir() {
+                errors.push(Error::Missing {
+                    path: root_dir.path.clone(),
+                });
+                continue;
+            }
+            self.root_dirs
+                .insert(host_path.clone(), ctx.config.depth_for(root_dir));
+            self.pending_roots.insert(host_path.clone());
+            scan_host_folder(&host_path);
+        }
+        self.unanswered = self.pending_roots.len();
+        self.scanning = self.unanswered > 0;
+        self.last_result = Some(Instant::now());
+        if self.scanning {
+            set_timeout(SCAN_SETTLE_SECS);
+        }
+        Found {
+            errors,
+            ..Found::default()
+        }
+    }
+
+    fn update(&mut self, event: &Event, ctx: &Context) -> Found {
+        match event {
+            Event::FileSystemUpdate(paths) => self.process_filesystem_update(paths, ctx).into(),
+            Event::Timer(_) => {
+                if !self.scanning {
+                    return Found::default();
+                }
+                // A single timer is armed again until no results arrived for the whole delay
+                let settle = Duration::from_secs_f64(SCAN_SETTLE_SECS);
+                let elapsed = self.last_result.map(|last| last.elapsed()).unwrap_or(settle);
+                match settle.checked_sub(elapsed) {
+                    Some(remaining) if !remaining.is_zero() => set_timeout(remaining.as_secs_f64()),
+                    _ => self.scanning = false,
+                }
+                Found::default()
+            }
+            _ => Found::default(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.scanning
+    }
+}
+
+impl ScanSource {
+    fn process_filesystem_update(
+        &mut self,
+        paths: &[(PathBuf, Option<FileMetadata>)],
+        ctx: &Context,
+    ) -> Vec<String> {
+        self.unanswered = self.unanswered.saturating_sub(1);
+        // Group the scanned entries by the directory they were found in
+        let mut scanned: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        for (path, _) in paths {
+            if let Some(parent) = path.parent() {
+                scanned
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .push(path.clone());
+            }
+        }
+
+        let mut dirs_with_root_files = HashSet::new();
+        let mut subdirs_to_scan = Vec::new();
+        for (parent, children) in scanned {
+            let (depth, max_depth) = if let Some(max_depth) = self.root_dirs.get(&parent) {
+                // This is a scan of a root directory
+                self.pending_roots.remove(&parent);
+                (0, *max_depth)
+            } else if let Some(scan) = self.pending_scans.remove(&parent) {
+                // This is a scan of a subdirectory we're checking
+                if is_project(&parent, &children, ctx) {
+                    // Found the root files! The parent directory is valid, stop descending
+                    dirs_with_root_files.insert(parent);
+                    continue;
+                }
+                scan
+            } else {
+                continue;
+            };
+            if depth >= max_depth {
+                continue;
+            }
+            subdirs_to_scan.extend(
+                children
+                    .into_iter()
+                    .filter(|p| p.is_dir() && !is_excluded(p, ctx))
+                    .map(|p| (p, (depth + 1, max_depth))),
+            );
+        }
+
+        // Scan subdirectories to check for root files
+        for (subdir, scan) in subdirs_to_scan {
+            if self.pending_scans.insert(subdir.clone(), scan).is_none() {
+                self.unanswered += 1;
+                scan_host_folder(&subdir);
+            }
+        }
+
+        let found = dirs_with_root_files
+            .into_iter()
+            .map(|dir| change_root(&dir, ctx.cwd).to_string_lossy().to_string())
+            .collect();
+
+        if self.unanswered == 0 {
+            self.pending_scans.clear();
+            self.scanning = false;
+        } else if self.scanning {
+            // Unreadable directories are never answered, so also finish once results
+            // stop coming. Results arriving later are still listed and cached.
+            self.last_result = Some(Instant::now());
+        }
+        found
+    }
+}
+
+// Paths outside of the mount are returned as is
+fn change_root(path: &Path, cwd: &Path) -> PathBuf {
+    match path.strip_prefix(ROOT) {
+        Ok(relative) => cwd.join(relative),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn is_project(dir: &Path, children: &[PathBuf], ctx: &Context) -> bool {
+    let names: Vec<&str> = children
+        .iter()
+        .filter_map(|p| p.file_name().and_then(|s| s.to_str()))
+        .collect();
+    let path = change_root(dir, ctx.cwd);
+    ctx.config.root_files.matches(&path.to_string_lossy(), &names)
+}
+
+// Directories that are never descended into while scanning
+fn is_excluded(dir: &Path, ctx: &Context) -> bool {
+    let path = change_root(dir, ctx.cwd).to_string_lossy().to_string();
+    let config = ctx.config;
+    (is_hidden(dir) && !config.include_hidden.is_match(&path))
+        || config.exclude.is_match(&path)
+        || config.root_files.excludes(&path)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|s| s.to_str())
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::fs;
+
+    // Answers a scan like the host does, with the entries of the dir
+    fn scan_result(dir: &Path) -> Event {
+        let entries = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| (entry.unwrap().path(), None))
+            .collect();
+        Event::FileSystemUpdate(entries)
+    }
+
+    #[test]
+    fn test_scan_stops_at_projects_and_max_depth() {
+        let root = std::env::temp_dir().join(format!("sessionizer-scan-{}", std::process::id()));
+        for dir in [
+            "api/.git",
+            "api/vendor/lib/.git",
+            "work/web/.git",
+            "work/old/app/.git",
+            ".cache/tool/.git",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let config = Config::default();
+        let ctx = Context {
+            config: &config,
+            cwd: Path::new("/"),
+            permissions_granted: true,
+        };
+        let mut source = ScanSource {
+            root_dirs: HashMap::from([(root.clone(), 2)]),
+            unanswered: 1,
+            scanning: true,
+            ..Default::default()
+        };
+        let mut found = source.update(&scan_result(&root), &ctx).dirs;
+        while let Some(dir) = source.pending_scans.keys().next().cloned() {
+            found.extend(source.update(&scan_result(&dir), &ctx).dirs);
+        }
+        fs::remove_dir_all(&root).unwrap();
+        found.sort();
+        let expected: Vec<String> = ["api", "work/web"]
+            .iter()
+            .map(|dir| root.join(dir).to_string_lossy().to_string())
+            .collect();
+        assert_eq!(found, expected);
+        assert!(!source.is_running());
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::store::{self, DataFile};
+
+const DATA_FILE: &str = "session_dirs";
+
+/// Remembers which directory each session was created for, zellij itself
+/// doesn't report a session's cwd.
+#[derive(Debug)]
+pub struct SessionDirs {
+    file: DataFile,
+    dirs: HashMap<String, String>,
+}
+
+impl Default for SessionDirs {
+    fn default() -> Self {
+        Self {
+            file: DataFile::new(Path::new(store::TMP_DIR), DATA_FILE),
+            dirs: HashMap::new(),
+        }
+    }
+}
+
+impl SessionDirs {
+    /// Loads the sessions kept in `dir`, where they are saved from then on
+    pub fn load(&mut self, dir: &Path) {
+        self.file = DataFile::new(dir, DATA_FILE);
+        self.dirs = self.file.read().map(|content| parse(&content)).unwrap_or_default();
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.file.write(&serialize(&self.dirs))
+    }
+
+    pub fn insert(&mut self, session_name: &str, dir: &str) {
+        self.dirs.insert(session_name.to_string(), dir.to_string());
+    }
+
+    pub fn rename(&mut self, session_name: &str, new_name: &str) {
+        if let Some(dir) = self.dirs.remove(session_name) {
+            self.dirs.insert(new_name.to_string(), dir);
+        }
+    }
+
+    pub fn get(&self, session_name: &str) -> Option<&String> {
+        self.dirs.get(session_name)
+    }
+
+    // The session created for the dir, the first by name if several were
+    pub fn session_for(&self, dir: &str) -> Option<&String> {
+        self.dirs
+            .iter()
+            .filter(|(_, d)| *d == dir)
+            .map(|(name, _)| name)
+            .min()
+    }
+
+    // Every (session, dir) pair, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.dirs.iter()
+    }
+}
+
+// One session per line: "<session>\t<dir>"
+fn parse(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, dir)| (name.to_string(), dir.to_string()))
+        .collect()
+}
+
+fn serialize(dirs: &HashMap<String, String>) -> String {
+    dirs.iter()
+        .map(|(name, dir)| format!("{name}\t{dir}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_for() {
+        let mut session_dirs = SessionDirs::default();
+        session_dirs.insert("work_api", "/home/me/work/api");
+        session_dirs.insert("api", "/home/me/oss/api");
+        session_dirs.insert("api_old", "/home/me/oss/api");
+        assert_eq!(session_dirs.session_for("/home/me/oss/api").map(String::as_str), Some("api"));
+        assert_eq!(session_dirs.session_for("/home/me/web"), None);
+    }
+}
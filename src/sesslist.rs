@@ -2,38 +2,81 @@ use std::collections::HashMap;
 
 use zellij_tile::prelude::*;
 
-use crate::filter;
+use crate::filter::{self, FilterMatch};
+use crate::marks::Marks;
+use crate::prompt::SessionAction;
 
 #[derive(Debug, Default)]
 pub struct Session {
     pub name: String,
     pub icon: String,
+    pub info: Option<SessionInfo>, // None for resurrectable sessions
+    pub dir: Option<String>,       // The project directory the session was created for
+}
+
+impl Session {
+    // Details rendered after the name: tab count, client count and project dir
+    fn details(&self) -> String {
+        let counts = match &self.info {
+            Some(info) => format!(
+                "{:>2} tabs {:>2} clients",
+                info.tabs.len(),
+                info.connected_clients
+            ),
+            None => format!("{:>16}", "resurrectable"),
+        };
+        match &self.dir {
+            Some(dir) => format!("{counts}  {dir}"),
+            None => counts,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct SessList {
     sessions: Vec<String>,
-    session_icons: HashMap<String, String>,
+    session_info: HashMap<String, Session>,
     cursor: usize,
 
     search_term: String,
-    filtered_sessions: Vec<String>,
+    filtered_sessions: Vec<FilterMatch>,
+    marks: Marks,
 }
 
 impl SessList {
     pub fn reset(&mut self) {
         self.sessions.clear();
-        self.session_icons.clear();
+        self.session_info.clear();
         self.cursor = 0;
         self.filtered_sessions.clear();
+        self.marks.clear();
     }
 
     pub fn update_sessions(&mut self, sessions: Vec<Session>) {
         self.sessions = sessions.iter().map(|s| s.name.clone()).collect();
-        self.session_icons = sessions.into_iter().map(|s| (s.name, s.icon)).collect();
+        self.session_info = sessions.into_iter().map(|s| (s.name.clone(), s)).collect();
         self.filter();
     }
 
+    pub fn sessions(&self) -> impl Iterator<Item = &Session> {
+        self.sessions.iter().filter_map(|name| self.session_info.get(name))
+    }
+
+    pub fn get_session(&self, name: &str) -> Option<&Session> {
+        self.session_info.get(name)
+    }
+
+    /// Kills the live sessions and deletes the resurrectable ones
+    pub fn kill_action(&self, sessions: Vec<String>) -> SessionAction {
+        let (resurrectable, live) = sessions.into_iter().partition(|session| {
+            self.get_session(session).is_some_and(|s| s.info.is_none())
+        });
+        SessionAction::Kill {
+            live,
+            resurrectable,
+        }
+    }
+
     pub fn handle_up(&mut self) {
         if self.cursor > 0 {
             self.cursor -= 1;
@@ -48,18 +91,31 @@ impl SessList {
 
     pub fn get_selected(&self) -> Option<String> {
         if self.cursor < self.filtered_sessions.len() {
-            Some(self.filtered_sessions[self.cursor].clone())
+            Some(self.filtered_sessions[self.cursor].item.clone())
         } else {
             None
         }
     }
 
-    pub fn kill_selected(&mut self) {
+    pub fn toggle_mark_selected(&mut self) {
         if let Some(selected) = self.get_selected() {
-            kill_sessions(&[selected]);
+            self.marks.toggle(selected);
         }
     }
 
+    pub fn mark_all_filtered(&mut self) {
+        self.marks.mark_all(self.filtered_sessions.iter().map(|m| m.item.clone()));
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marks.clear();
+    }
+
+    // Marked entries in list order
+    pub fn marked(&self) -> Vec<String> {
+        self.marks.in_order(&self.sessions)
+    }
+
     pub fn set_search_term(&mut self, search_term: &str) {
         self.search_term = search_term.to_string();
         self.filter();
@@ -69,7 +125,7 @@ impl SessList {
         self.filtered_sessions = filter::fuzzy_filter(&self.sessions, self.search_term.as_str());
     }
 
-    pub fn render(&self, rows: usize, _cols: usize) {
+    pub fn render(&self, rows: usize, cols: usize) {
         let from = self
             .cursor
             .saturating_sub(rows.saturating_sub(1) / 2)
@@ -80,26 +136,69 @@ impl SessList {
                 println!();
             }
         }
+        let name_width = self
+            .filtered_sessions
+            .iter()
+            .map(|sess| sess.item.chars().count())
+            .max()
+            .unwrap_or(0);
         self.filtered_sessions
             .iter()
             .enumerate()
             .skip(from)
             .take(rows)
             .for_each(|(i, sess)| {
-                let text = self
-                    .session_icons
-                    .get(sess)
-                    .map(|icon| format!("{icon} {sess}"))
-                    .unwrap()
-                    .to_string();
-                let text_len = text.len();
-                let item = Text::new(text);
-                let item = match i == self.cursor {
-                    true => item.color_range(0, 0..text_len).selected(),
-                    false => item,
+                let Some(session) = self.session_info.get(&sess.item) else {
+                    return;
+                };
+                let icon = &session.icon;
+                let marked = self.marks.contains(&sess.item);
+                let mark = self.marks.prefix(&sess.item);
+                let name = format!("{mark}{icon} {:<name_width$}", sess.item);
+                let name_len = name.chars().count();
+                let text = format!("{name}  {}", session.details());
+                let text: String = text.chars().take(cols).collect();
+                let text_len = text.chars().count();
+                // Shift the match indices past the mark, the icon and its separator
+                let offset = mark.len() + icon.chars().count() + 1;
+                let indices = sess.indices.iter().map(|i| i + offset).collect();
+                let item = Text::new(text).color_indices(3, indices);
+                let item = match (i == self.cursor, marked) {
+                    (true, _) => item.color_range(0, 0..text_len).selected(),
+                    (false, true) => item.color_range(1, 0..text_len),
+                    // The details are secondary, like the hints of the status line
+                    (false, false) => item.color_range(1, name_len.min(text_len)..),
                 };
                 print_text(item);
                 println!();
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_action_deletes_resurrectable_sessions() {
+        let mut sesslist = SessList::default();
+        let session = |name: &str, info| Session {
+            name: name.to_string(),
+            info,
+            ..Default::default()
+        };
+        sesslist.update_sessions(vec![
+            session("api", Some(SessionInfo::default())),
+            session("old", None),
+            session("web", Some(SessionInfo::default())),
+        ]);
+        let sessions = ["web", "old", "api"].map(String::from).to_vec();
+        assert_eq!(
+            sesslist.kill_action(sessions),
+            SessionAction::Kill {
+                live: vec!["web".to_string(), "api".to_string()],
+                resurrectable: vec!["old".to_string()],
+            }
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::path::Path;
+
+use zellij_tile::prelude::*;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::scan::ScanSource;
+use crate::worktree::{Worktree, WorktreeSource};
+use crate::zoxide::ZoxideSource;
+use crate::COMMAND_KEY;
+
+pub const DIRS_COMMAND: &str = "dirs_command";
+
+/// Where a dir comes from. A dir found by several sources is tagged after the
+/// one declared last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Source {
+    Cache,
+    Zoxide,
+    Command,
+    Static,
+    Worktree,
+    Scan,
+}
+
+impl Source {
+    // Shown after the dirs that aren't projects of the root dirs
+    pub fn tag(&self) -> Option<&'static str> {
+        match self {
+            Source::Zoxide => Some("zoxide"),
+            Source::Command => Some("command"),
+            _ => None,
+        }
+    }
+}
+
+/// What a source needs to know about the plugin
+pub struct Context<'a> {
+    pub config: &'a Config,
+    pub cwd: &'a Path,
+    pub permissions_granted: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Found {
+    pub dirs: Vec<String>,
+    pub errors: Vec<Error>,
+    // Ranking boost per dir, replacing the ones the source found before
+    pub boosts: HashMap<String, u32>,
+    // The worktrees among the dirs, listed below their main repo
+    pub worktrees: Vec<Worktree>,
+}
+
+impl From<Vec<String>> for Found {
+    fn from(dirs: Vec<String>) -> Self {
+        Self {
+            dirs,
+            ..Self::default()
+        }
+    }
+}
+
+pub trait DirSource: Debug {
+    fn source(&self) -> Source;
+
+    /// Starts looking for dirs, returning the ones known right away
+    fn start(&mut self, ctx: &Context) -> Found;
+
+    /// Dirs found thanks to an event, e.g. the result of a command the source ran
+    fn update(&mut self, _event: &Event, _ctx: &Context) -> Found {
+        Found::default()
+    }
+
+    /// Looks for more dirs in the ones `source` listed, e.g. the worktrees of a repo
+    fn listed(&mut self, _source: Source, _dirs: &[String], _ctx: &Context) {}
+
+    /// The cached dirs are replaced once no source is running anymore
+    fn is_running(&self) -> bool {
+        false
+    }
+}
+
+/// Every source, the cached dirs are listed by the plugin before they start
+pub fn all() -> Vec<Box<dyn DirSource>> {
+    vec![
+        Box::new(StaticSource),
+        Box::new(ScanSource::default()),
+        Box::new(WorktreeSource::default()),
+        Box::new(CommandSource::default()),
+        Box::new(ZoxideSource::default()),
+    ]
+}
+
+/// Runs the command now if permitted, otherwise once the permissions are granted.
+/// It is running until its output arrives or the permissions are denied.
+#[derive(Debug, Default)]
+pub struct PendingCommand {
+    waiting: bool,
+    running: bool,
+}
+
+impl PendingCommand {
+    pub fn start(&mut self, ctx: &Context, run: impl Fn()) {
+        self.waiting = !ctx.permissions_granted;
+        self.running = true;
+        if ctx.permissions_granted {
+            run();
+        }
+    }
+
+    pub fn update(&mut self, event: &Event, run: impl Fn()) {
+        match event {
+            Event::PermissionRequestResult(status) if self.waiting => {
+                self.waiting = false;
+                match status {
+                    PermissionStatus::Granted => run(),
+                    PermissionStatus::Denied => self.running = false,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.running = false;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
+
+// The output of a command that was run with `key` as its COMMAND_KEY
+pub fn command_output<'a>(event: &'a Event, key: &str) -> Option<Result<&'a [u8], Error>> {
+    match event {
+        Event::RunCommandResult(exit_code, stdout, stderr, context)
+            if context.get(COMMAND_KEY).map(String::as_str) == Some(key) =>
+        {
+            match exit_code {
+                Some(0) => Some(Ok(stdout)),
+                _ => Some(Err(Error::Command {
+                    command: key.to_string(),
+                    stderr: String::from_utf8_lossy(stderr).to_string(),
+                })),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The `dirs` of the config, listed as is
+#[derive(Debug)]
+pub struct StaticSource;
+
+impl DirSource for StaticSource {
+    fn source(&self) -> Source {
+        Source::Static
+    }
+
+    fn start(&mut self, ctx: &Context) -> Found {
+        ctx.config
+            .dirs
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// The dirs printed by the `dirs_command` of the config, one per line
+#[derive(Debug, Default)]
+pub struct CommandSource {
+    command: Option<String>,
+    pending: PendingCommand,
+}
+
+impl CommandSource {
+    fn run(command: &str) {
+        let context = BTreeMap::from([(COMMAND_KEY.to_string(), DIRS_COMMAND.to_string())]);
+        run_command(&["sh", "-c", command], context);
+    }
+}
+
+impl DirSource for CommandSource {
+    fn source(&self) -> Source {
+        Source::Command
+    }
+
+    fn start(&mut self, ctx: &Context) -> Found {
+        self.command = ctx.config.dirs_command.clone();
+        if let Some(command) = &self.command {
+            self.pending.start(ctx, || Self::run(command));
+        }
+        Found::default()
+    }
+
+    fn update(&mut self, event: &Event, _ctx: &Context) -> Found {
+        if let Some(command) = &self.command {
+            self.pending.update(event, || Self::run(command));
+        }
+        let output = command_output(event, DIRS_COMMAND);
+        if output.is_some() {
+            self.pending.finish();
+        }
+        match output {
+            Some(Ok(stdout)) => String::from_utf8_lossy(stdout)
+                .lines()
+                .map(|line| line.trim_end_matches('/').to_string())
+                .filter(|dir| !dir.is_empty())
+                .collect::<Vec<_>>()
+                .into(),
+            Some(Err(error)) => Found {
+                errors: vec![error],
+                ..Found::default()
+            },
+            None => Found::default(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.pending.is_running()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_source_output() {
+        let config = Config {
+            dirs_command: Some("ls -d ~/notes".to_string()),
+            ..Config::default()
+        };
+        let ctx = Context {
+            config: &config,
+            cwd: Path::new("/"),
+            permissions_granted: false,
+        };
+        let result = |exit_code, stdout: &str, key: &str| {
+            let context = BTreeMap::from([(COMMAND_KEY.to_string(), key.to_string())]);
+            Event::RunCommandResult(exit_code, stdout.as_bytes().to_vec(), vec![], context)
+        };
+        let mut source = CommandSource::default();
+        // Waits for the permissions, which keeps the cached dirs listed meanwhile
+        source.start(&ctx);
+        assert!(source.is_running());
+        let found = source.update(&result(Some(0), "/home/me/notes/\n\n/srv/www\n", DIRS_COMMAND), &ctx);
+        assert_eq!(found.dirs, vec!["/home/me/notes", "/srv/www"]);
+        assert!(!source.is_running());
+        let found = source.update(&result(Some(1), "", DIRS_COMMAND), &ctx);
+        assert!(found.dirs.is_empty());
+        assert_eq!(found.errors.len(), 1);
+        let found = source.update(&result(Some(0), "/home/me/api\n", "other"), &ctx);
+        assert!(found.dirs.is_empty() && found.errors.is_empty());
+    }
+}
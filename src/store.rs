@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::paths;
+
+/// Zellij mounts a new /data for every plugin instance, so the data files are
+/// kept in its temp dir instead, which every session of the user shares
+pub const TMP_DIR: &str = "/tmp/zellij-sessionizer";
+
+/// The dir holding the data files: `data_dir` when set, it's reached through
+/// the plugin cwd like the root dirs
+pub fn dir(config: &Config, cwd: &Path) -> Result<PathBuf, Error> {
+    match &config.data_dir {
+        Some(dir) => paths::to_host(dir, cwd).ok_or_else(|| Error::UnreachableDataDir {
+            path: dir.clone(),
+            cwd: cwd.to_path_buf(),
+        }),
+        None => Ok(PathBuf::from(TMP_DIR)),
+    }
+}
+
+/// Like `dir`, the temp dir is used when `data_dir` can't be reached
+pub fn dir_or_tmp(config: &Config, cwd: &Path) -> PathBuf {
+    dir(config, cwd).unwrap_or_else(|_| PathBuf::from(TMP_DIR))
+}
+
+/// A file the plugin remembers something in between loads
+#[derive(Debug, Clone)]
+pub struct DataFile {
+    path: PathBuf,
+}
+
+impl DataFile {
+    pub fn new(dir: &Path, name: &str) -> Self {
+        Self {
+            path: dir.join(name),
+        }
+    }
+
+    /// None when the file doesn't exist yet or can't be read
+    pub fn read(&self) -> Option<String> {
+        fs::read_to_string(&self.path).ok()
+    }
+
+    pub fn write(&self, content: &str) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_dir_is_reached_through_the_cwd() {
+        let mut config = Config::default();
+        let cwd = Path::new("/home/me");
+        assert_eq!(dir(&config, cwd).ok(), Some(PathBuf::from(TMP_DIR)));
+        config.data_dir = Some(PathBuf::from("/home/me/.local/share/sessionizer"));
+        assert_eq!(dir(&config, cwd).ok(), Some(PathBuf::from("/host/.local/share/sessionizer")));
+        config.data_dir = Some(PathBuf::from("/var/lib/sessionizer"));
+        assert!(dir(&config, cwd).is_err());
+    }
+}
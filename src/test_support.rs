@@ -0,0 +1,6 @@
+// Native test builds have no zellij host to provide the shim's imports. Code
+// under test that calls into the host, e.g. the scan through scan_host_folder,
+// writes the command to stdout and then reaches this stub, which makes the
+// call a no-op.
+#[no_mangle]
+extern "C" fn host_run_plugin_command() {}
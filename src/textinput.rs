@@ -5,6 +5,7 @@ type Color = usize;
 #[derive(Debug)]
 pub struct TextInput {
     text: Vec<char>,
+    cursor: usize, // Char position, text.len() when at the end
 
     cursor_symbol: char,
     marker_symbol: char,
@@ -17,6 +18,7 @@ impl Default for TextInput {
     fn default() -> Self {
         Self {
             text: Vec::new(),
+            cursor: 0,
 
             marker_symbol: '>',
             cursor_symbol: '_',
@@ -30,41 +32,170 @@ impl Default for TextInput {
 impl TextInput {
     pub fn reset(&mut self) {
         self.text.clear();
+        self.cursor = 0;
     }
 
     pub fn get_text(&self) -> String {
         self.text.iter().collect::<String>()
     }
 
+    /// Handles the readline style editing keys, returns whether the text
+    /// changed. Moving the cursor or keys that are not for the text input
+    /// leave it unchanged.
+    pub fn handle_key(&mut self, key: &KeyWithModifier) -> bool {
+        let text = self.text.clone();
+        let ctrl = key.key_modifiers.contains(&KeyModifier::Ctrl);
+        let alt = key.key_modifiers.contains(&KeyModifier::Alt);
+        match key.bare_key {
+            BareKey::Backspace if ctrl || alt => self.delete_word_backward(),
+            BareKey::Backspace => self.handle_backspace(),
+            BareKey::Delete => self.handle_delete(),
+            BareKey::Left if ctrl || alt => self.move_word_left(),
+            BareKey::Left => self.cursor = self.cursor.saturating_sub(1),
+            BareKey::Right if ctrl || alt => self.move_word_right(),
+            BareKey::Right => self.cursor = (self.cursor + 1).min(self.text.len()),
+            BareKey::Home => self.cursor = 0,
+            BareKey::End => self.cursor = self.text.len(),
+            BareKey::Char('a') if ctrl => self.cursor = 0,
+            BareKey::Char('e') if ctrl => self.cursor = self.text.len(),
+            BareKey::Char('b') if ctrl => self.cursor = self.cursor.saturating_sub(1),
+            BareKey::Char('f') if ctrl => self.cursor = (self.cursor + 1).min(self.text.len()),
+            BareKey::Char('b') if alt => self.move_word_left(),
+            BareKey::Char('f') if alt => self.move_word_right(),
+            BareKey::Char('h') if ctrl => self.handle_backspace(),
+            BareKey::Char('d') if ctrl => self.handle_delete(),
+            BareKey::Char('w') if ctrl => self.delete_word_backward(),
+            BareKey::Char('u') if ctrl => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            BareKey::Char('k') if ctrl => self.text.truncate(self.cursor),
+            BareKey::Char(c) if !ctrl && !alt => self.handle_char(c),
+            _ => {}
+        }
+        self.text != text
+    }
+
     pub fn handle_backspace(&mut self) {
-        self.text.pop();
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn handle_delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
     }
 
     pub fn handle_char(&mut self, c: char) {
         if c == '\n' || c == '\r' || c == '\t' {
             return;
         }
-        self.text.push(c);
+        self.text.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    /// Inserts text at the cursor, line breaks are turned into spaces.
+    /// Zellij 0.41 has no paste event for plugins, pasted text arrives as
+    /// separate key presses and goes through `handle_key`.
+    pub fn insert_text(&mut self, txt: &str) {
+        txt.chars()
+            .map(|c| if c == '\n' || c == '\r' || c == '\t' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .for_each(|c| self.handle_char(c));
+    }
+
+    fn move_word_left(&mut self) {
+        while self.cursor > 0 && !self.text[self.cursor - 1].is_alphanumeric() {
+            self.cursor -= 1;
+        }
+        while self.cursor > 0 && self.text[self.cursor - 1].is_alphanumeric() {
+            self.cursor -= 1;
+        }
+    }
+
+    fn move_word_right(&mut self) {
+        while self.cursor < self.text.len() && !self.text[self.cursor].is_alphanumeric() {
+            self.cursor += 1;
+        }
+        while self.cursor < self.text.len() && self.text[self.cursor].is_alphanumeric() {
+            self.cursor += 1;
+        }
+    }
+
+    // Like readline's ctrl+w, words are separated by whitespace
+    fn delete_word_backward(&mut self) {
+        let end = self.cursor;
+        while self.cursor > 0 && self.text[self.cursor - 1].is_whitespace() {
+            self.cursor -= 1;
+        }
+        while self.cursor > 0 && !self.text[self.cursor - 1].is_whitespace() {
+            self.cursor -= 1;
+        }
+        self.text.drain(self.cursor..end);
     }
 
     pub fn replace_text(&mut self, txt: &str) -> String {
         let prev_text = self.text.iter().collect::<String>();
         self.reset();
-        self.text = txt.chars().collect();
+        self.insert_text(txt);
         prev_text
     }
 
     pub fn render(&self, _rows: usize, _cols: usize) {
         let search_term = self.text.iter().collect::<String>();
+        // The cursor is drawn over the char it is on, or after the text
+        let cursor_symbol = match self.cursor == self.text.len() {
+            true => self.cursor_symbol.to_string(),
+            false => "".to_string(),
+        };
         let search_bar_content = format!(
             "{} {}{}",
-            self.marker_symbol, search_term, self.cursor_symbol
+            self.marker_symbol, search_term, cursor_symbol
         );
-        let search_bar_len = search_bar_content.len();
+        let search_bar_len = search_bar_content.chars().count();
+        let cursor_pos = 2 + self.cursor;
         let search_bar = Text::new(search_bar_content)
             .color_range(self.marker_color, 0..1)
-            .color_range(self.text_color, 2..search_bar_len)
-            .color_range(self.cursor_color, search_bar_len..search_bar_len + 1);
+            .color_range(self.text_color, 2..cursor_pos)
+            .color_range(self.text_color, cursor_pos + 1..search_bar_len)
+            .color_range(self.cursor_color, cursor_pos..cursor_pos + 1);
         print_text(search_bar);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(input: &mut TextInput, text: &str) {
+        text.chars().for_each(|c| {
+            input.handle_key(&KeyWithModifier::new(BareKey::Char(c)));
+        });
+    }
+
+    #[test]
+    fn test_editing_mid_string() {
+        let mut input = TextInput::default();
+        type_text(&mut input, "my projct");
+        assert!(!input.handle_key(&KeyWithModifier::new(BareKey::Left)));
+        input.handle_key(&KeyWithModifier::new(BareKey::Left));
+        type_text(&mut input, "e");
+        assert_eq!(input.get_text(), "my project");
+
+        input.handle_key(&KeyWithModifier::new(BareKey::End));
+        assert!(input.handle_key(&KeyWithModifier::new(BareKey::Char('w')).with_ctrl_modifier()));
+        assert_eq!(input.get_text(), "my ");
+
+        type_text(&mut input, "repo");
+        input.handle_key(&KeyWithModifier::new(BareKey::Char('a')).with_ctrl_modifier());
+        input.handle_key(&KeyWithModifier::new(BareKey::Delete));
+        assert_eq!(input.get_text(), "y repo");
+
+        input.handle_key(&KeyWithModifier::new(BareKey::Right).with_alt_modifier());
+        input.handle_key(&KeyWithModifier::new(BareKey::Char('u')).with_ctrl_modifier());
+        assert_eq!(input.get_text(), " repo");
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use zellij_tile::prelude::*;
+
+use crate::filter::{self, FilterMatch};
+use crate::sesslist::Session;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Dir(String),
+    Session(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub target: Target,
+    pub session: Option<String>, // Live or resurrectable session for this entry
+    icon: String,
+}
+
+/// Directories and sessions merged into a single list, each directory is
+/// annotated with its session and sessions without a directory are listed too.
+#[derive(Debug, Default)]
+pub struct UnifiedList {
+    items: Vec<String>,
+    entries: HashMap<String, Entry>,
+    cursor: usize,
+
+    search_term: String,
+    filtered_items: Vec<FilterMatch>,
+    boosts: HashMap<String, u32>,
+}
+
+impl UnifiedList {
+    pub fn reset(&mut self) {
+        self.items.clear();
+        self.entries.clear();
+        self.cursor = 0;
+        self.filtered_items.clear();
+    }
+
+    pub fn update<'a>(&mut self, dirs: &[String], sessions: impl Iterator<Item = &'a Session>) {
+        let mut entries: HashMap<String, Entry> = dirs
+            .iter()
+            .map(|dir| {
+                let entry = Entry {
+                    target: Target::Dir(dir.clone()),
+                    session: None,
+                    icon: " ".to_string(),
+                };
+                (dir.clone(), entry)
+            })
+            .collect();
+        let mut items = dirs.to_vec();
+        for session in sessions {
+            let dir_entry = session.dir.as_ref().and_then(|dir| entries.get_mut(dir));
+            if let Some(entry) = dir_entry.filter(|entry| entry.session.is_none()) {
+                entry.session = Some(session.name.clone());
+                entry.icon = session.icon.clone();
+                continue;
+            }
+            // Listed once, and never in place of a dir with the same path
+            if entries.contains_key(&session.name) {
+                continue;
+            }
+            items.push(session.name.clone());
+            entries.insert(
+                session.name.clone(),
+                Entry {
+                    target: Target::Session(session.name.clone()),
+                    session: Some(session.name.clone()),
+                    icon: session.icon.clone(),
+                },
+            );
+        }
+        let changed = items != self.items;
+        self.items = items;
+        self.entries = entries;
+        if changed {
+            self.filter();
+        }
+    }
+
+    pub fn set_boosts(&mut self, boosts: HashMap<String, u32>) {
+        self.boosts = boosts;
+        self.filter();
+    }
+
+    pub fn handle_up(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+        }
+    }
+
+    pub fn handle_down(&mut self) {
+        if self.cursor < self.filtered_items.len().saturating_sub(1) {
+            self.cursor += 1;
+        }
+    }
+
+    pub fn get_selected(&self) -> Option<Entry> {
+        self.filtered_items
+            .get(self.cursor)
+            .and_then(|m| self.entries.get(&m.item))
+            .cloned()
+    }
+
+    pub fn set_search_term(&mut self, search_term: &str) {
+        self.search_term = search_term.to_string();
+        self.filter();
+    }
+
+    pub fn filter(&mut self) {
+        self.filtered_items =
+            filter::ranked_fuzzy_filter(&self.items, self.search_term.as_str(), &self.boosts);
+        self.cursor = self.filtered_items.len().saturating_sub(1);
+    }
+
+    pub fn render(&self, rows: usize, cols: usize) {
+        let from = self
+            .cursor
+            .saturating_sub(rows.saturating_sub(1) / 2)
+            .min(self.filtered_items.len().saturating_sub(rows));
+        let missing_rows = rows.saturating_sub(self.filtered_items.len());
+        if missing_rows > 0 {
+            for _ in 0..missing_rows {
+                println!();
+            }
+        }
+        self.filtered_items
+            .iter()
+            .enumerate()
+            .skip(from)
+            .take(rows)
+            .for_each(|(i, m)| {
+                let Some(entry) = self.entries.get(&m.item) else {
+                    return;
+                };
+                let text = format!("{} {}", entry.icon, m.item);
+                let text: String = text.chars().take(cols).collect();
+                let text_len = text.len();
+                // Shift the match indices past the icon and its separator
+                let offset = entry.icon.chars().count() + 1;
+                let indices = m.indices.iter().map(|i| i + offset).collect();
+                let item = Text::new(text).color_indices(3, indices);
+                let item = match i == self.cursor {
+                    true => item.color_range(0, 0..text_len).selected(),
+                    false => item,
+                };
+                print_text(item);
+                println!();
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_merges_dirs_and_sessions() {
+        let session = |name: &str, dir: Option<&str>| Session {
+            name: name.to_string(),
+            icon: " ".to_string(),
+            info: None,
+            dir: dir.map(str::to_string),
+        };
+        let dirs = vec!["/home/me/api".to_string(), "/home/me/web".to_string()];
+        let sessions = [
+            session("api", Some("/home/me/api")),
+            // A second session of the same dir is listed on its own
+            session("api_old", Some("/home/me/api")),
+            session("scratch", None),
+            session("scratch", None),
+        ];
+        let mut list = UnifiedList::default();
+        list.update(&dirs, sessions.iter());
+        assert_eq!(list.items, vec!["/home/me/api", "/home/me/web", "api_old", "scratch"]);
+        assert_eq!(list.entries["/home/me/api"].session.as_deref(), Some("api"));
+        assert_eq!(list.entries["/home/me/web"].session, None);
+        assert_eq!(list.entries["scratch"].target, Target::Session("scratch".to_string()));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use zellij_tile::prelude::*;
+
+use crate::sources::{command_output, Context, DirSource, Found, PendingCommand, Source};
+use crate::{paths, COMMAND_KEY};
+
+pub const WORKTREE_LIST: &str = "git_worktree_list";
+// Context key of the repo a worktree list was asked for
+const REPO_KEY: &str = "repo";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Worktree {
+    pub path: String,
+    pub repo: String, // Path of the main worktree
+    pub branch: String,
+}
+
+impl Worktree {
+    pub fn session_name(&self) -> String {
+        let repo_name = Path::new(&self.repo)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("{}@{}", repo_name, self.branch)
+    }
+}
+
+/// The worktrees of the git repos found by the scan, when enabled in the config
+#[derive(Debug, Default)]
+pub struct WorktreeSource {
+    // The command listing the worktrees of each repo
+    repos: HashMap<String, PendingCommand>,
+}
+
+impl DirSource for WorktreeSource {
+    fn source(&self) -> Source {
+        Source::Worktree
+    }
+
+    fn start(&mut self, _ctx: &Context) -> Found {
+        // The worktrees of the last scan are kept until their repo is listed again
+        self.repos.clear();
+        Found::default()
+    }
+
+    fn listed(&mut self, source: Source, dirs: &[String], ctx: &Context) {
+        if !ctx.config.git_worktrees || source != Source::Scan {
+            return;
+        }
+        for dir in dirs {
+            // Only main worktrees have a .git dir, the others have a .git file
+            let is_repo = paths::to_host(&Path::new(dir).join(".git"), ctx.cwd)
+                .is_some_and(|dot_git| dot_git.is_dir());
+            if is_repo && !self.repos.contains_key(dir) {
+                let pending = self.repos.entry(dir.clone()).or_default();
+                pending.start(ctx, || list_worktrees(dir));
+            }
+        }
+    }
+
+    fn update(&mut self, event: &Event, ctx: &Context) -> Found {
+        for (repo, pending) in &mut self.repos {
+            pending.update(event, || list_worktrees(repo));
+        }
+        let Event::RunCommandResult(_, _, _, context) = event else {
+            return Found::default();
+        };
+        let (Some(output), Some(repo)) = (command_output(event, WORKTREE_LIST), context.get(REPO_KEY))
+        else {
+            return Found::default();
+        };
+        if let Some(pending) = self.repos.get_mut(repo) {
+            pending.finish();
+        }
+        // Failing repos, e.g. with a git too old for worktrees, are listed without them
+        let Ok(stdout) = output else {
+            return Found::default();
+        };
+        let worktrees: Vec<Worktree> = parse_worktrees(&String::from_utf8_lossy(stdout))
+            .into_iter()
+            .filter(|wt| !ctx.config.exclude.is_match(&wt.path))
+            .collect();
+        Found {
+            dirs: worktrees.iter().map(|wt| wt.path.clone()).collect(),
+            worktrees,
+            ..Found::default()
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.repos.values().any(PendingCommand::is_running)
+    }
+}
+
+fn list_worktrees(repo: &str) {
+    let context = BTreeMap::from([
+        (COMMAND_KEY.to_string(), WORKTREE_LIST.to_string()),
+        (REPO_KEY.to_string(), repo.to_string()),
+    ]);
+    run_command(&["git", "-C", repo, "worktree", "list", "--porcelain"], context);
+}
+
+/// Parses the output of `git worktree list --porcelain`, the main worktree
+/// is listed first and is not returned. Bare entries are skipped.
+pub fn parse_worktrees(output: &str) -> Vec<Worktree> {
+    let mut repo: Option<String> = None;
+    let mut worktrees = vec![];
+    for block in output.split("\n\n") {
+        let mut path = None;
+        let mut branch = None;
+        let mut bare = false;
+        for line in block.lines() {
+            if let Some(p) = line.strip_prefix("worktree ") {
+                path = Some(p.to_string());
+            } else if let Some(b) = line.strip_prefix("branch ") {
+                branch = Some(b.trim_start_matches("refs/heads/").to_string());
+            } else if let Some(head) = line.strip_prefix("HEAD ") {
+                // Detached worktrees are named after their commit
+                branch = branch.or(Some(head.chars().take(7).collect()));
+            } else if line == "bare" {
+                bare = true;
+            }
+        }
+        let Some(path) = path else { continue };
+        match &repo {
+            None => repo = Some(path),
+            Some(repo) if !bare => worktrees.push(Worktree {
+                path,
+                repo: repo.clone(),
+                branch: branch.unwrap_or_default(),
+            }),
+            Some(_) => {}
+        }
+    }
+    worktrees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_parse_worktrees() {
+        let output = "worktree /home/me/api\nHEAD 1234567890\nbranch refs/heads/main\n\n\
+                      worktree /tmp/api-fix\nHEAD abcdef0123\nbranch refs/heads/fix/login\n\n\
+                      worktree /tmp/api-old\nHEAD fedcba9876\ndetached\n";
+        let worktrees = parse_worktrees(output);
+        assert_eq!(worktrees.len(), 2);
+        assert_eq!(worktrees[0].path, "/tmp/api-fix");
+        assert_eq!(worktrees[0].session_name(), "api@fix/login");
+        assert_eq!(worktrees[1].session_name(), "api@fedcba9");
+    }
+
+    #[test]
+    fn test_worktree_source_waits_for_every_repo() {
+        let config = Config {
+            git_worktrees: true,
+            ..Config::default()
+        };
+        let ctx = Context {
+            config: &config,
+            cwd: Path::new("/"),
+            permissions_granted: false,
+        };
+        let mut source = WorktreeSource::default();
+        for repo in ["/home/me/api", "/home/me/web"] {
+            let pending = source.repos.entry(repo.to_string()).or_default();
+            pending.start(&ctx, || {});
+        }
+        let context = BTreeMap::from([
+            (COMMAND_KEY.to_string(), WORKTREE_LIST.to_string()),
+            (REPO_KEY.to_string(), "/home/me/api".to_string()),
+        ]);
+        let output = "worktree /home/me/api\nbranch refs/heads/main\n\n\
+                      worktree /tmp/api-fix\nbranch refs/heads/fix\n";
+        let event = Event::RunCommandResult(Some(0), output.as_bytes().to_vec(), vec![], context);
+        let found = source.update(&event, &ctx);
+        assert_eq!(found.dirs, vec!["/tmp/api-fix"]);
+        assert_eq!(found.worktrees.len(), 1);
+        // Still waiting on the web repo
+        assert!(source.is_running());
+    }
+}
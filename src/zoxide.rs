@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashMap};
+
+use zellij_tile::prelude::*;
+
+use crate::sources::{command_output, Context, DirSource, Found, PendingCommand, Source};
+use crate::COMMAND_KEY;
+
+pub const ZOXIDE_QUERY: &str = "zoxide_query";
+
+/// The dirs known to zoxide, when enabled in the config
+#[derive(Debug, Default)]
+pub struct ZoxideSource {
+    pending: PendingCommand,
+}
+
+impl DirSource for ZoxideSource {
+    fn source(&self) -> Source {
+        Source::Zoxide
+    }
+
+    fn start(&mut self, ctx: &Context) -> Found {
+        if ctx.config.zoxide {
+            self.pending.start(ctx, query);
+        }
+        Found::default()
+    }
+
+    fn update(&mut self, event: &Event, ctx: &Context) -> Found {
+        self.pending.update(event, query);
+        let output = command_output(event, ZOXIDE_QUERY);
+        if output.is_some() {
+            self.pending.finish();
+        }
+        match output {
+            Some(Ok(stdout)) => {
+                let scores = parse_scores(&String::from_utf8_lossy(stdout));
+                let dirs = scores
+                    .keys()
+                    .filter(|dir| !ctx.config.exclude.is_match(dir))
+                    .cloned()
+                    .collect();
+                Found {
+                    dirs,
+                    boosts: scores,
+                    ..Found::default()
+                }
+            }
+            Some(Err(error)) => Found {
+                errors: vec![error],
+                ..Found::default()
+            },
+            None => Found::default(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.pending.is_running()
+    }
+}
+
+fn query() {
+    let context = BTreeMap::from([(COMMAND_KEY.to_string(), ZOXIDE_QUERY.to_string())]);
+    run_command(&["zoxide", "query", "--list", "--score"], context);
+}
+
+/// Parses the output of `zoxide query -l -s`, one "<score> <dir>" per line.
+/// The scores are unbounded, so they are normalized before being used as boosts.
+pub fn parse_scores(output: &str) -> HashMap<String, u32> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (score, dir) = line.trim_start().split_once(' ')?;
+            let score: f64 = score.parse().ok()?;
+            Some((dir.trim_start().to_string(), score.round() as u32))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scores() {
+        let output = "  48.0 /home/me/api\n   2.5 /home/me/my notes\nbroken\n";
+        let scores = parse_scores(output);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores["/home/me/api"], 48);
+        assert_eq!(scores["/home/me/my notes"], 3);
+    }
+}